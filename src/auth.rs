@@ -2,15 +2,15 @@
 //!
 //! These API endpoints are used to manage users and roles.
 
+use futures::future::FutureExt;
 use hyper::client::connect::Connect;
 use hyper::{StatusCode, Uri};
 use serde_derive::{Deserialize, Serialize};
-use serde_json;
 use std::future::Future;
 
-use crate::client::{Client, ClusterInfo, Response};
-use crate::error::{ApiError, Error};
-use crate::first_ok::{first_ok, Result};
+use crate::client::{build_url, Client, ClusterInfo, Response};
+use crate::error::Error;
+use crate::first_ok::{first_ok, Idempotency, Result};
 
 /// The structure returned by the `GET /v2/auth/enable` endpoint.
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -416,9 +416,11 @@ pub fn create_role<C>(client: &Client<C>, role: Role) -> impl Future<Output = Re
 where
     C: Clone + Connect + Sync + Send + 'static,
 {
-    let http_client = client.http_client().clone();
+    // A role that was created makes a repeated create fail because it already exists.
+    let idempotency = Idempotency::NonIdempotent;
+    let http_client = client.http_client().with_idempotency(&idempotency);
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
         let role = role.clone();
 
@@ -429,7 +431,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             match status {
                 StatusCode::OK | StatusCode::CREATED => {
@@ -449,9 +451,11 @@ pub fn create_user<C>(client: &Client<C>, user: NewUser) -> impl Future<Output =
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    let http_client = client.http_client().clone();
+    // A user that was created makes a repeated create fail because it already exists.
+    let idempotency = Idempotency::NonIdempotent;
+    let http_client = client.http_client().with_idempotency(&idempotency);
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
         let user = user.clone();
 
@@ -462,7 +466,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             match status {
                 StatusCode::OK | StatusCode::CREATED => {
@@ -483,10 +487,13 @@ where
     C: Clone + Connect + Sync + Send + 'static,
     N: Into<String>,
 {
-    let http_client = client.http_client().clone();
     let name = name.into();
+    // A delete that was applied fails with a missing role when it is made again, so if an attempt
+    // fails without a clear outcome, looking it up shows whether it was applied.
+    let idempotency = verify_deleted(client, format!("/roles/{}", name));
+    let http_client = client.http_client().with_idempotency(&idempotency);
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
         let name = name.clone();
 
@@ -514,10 +521,13 @@ where
     C: Clone + Connect + Sync + Send + 'static,
     N: Into<String>,
 {
-    let http_client = client.http_client().clone();
     let name = name.into();
+    // A delete that was applied fails with a missing user when it is made again, so if an attempt
+    // fails without a clear outcome, looking it up shows whether it was applied.
+    let idempotency = verify_deleted(client, format!("/users/{}", name));
+    let http_client = client.http_client().with_idempotency(&idempotency);
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
         let name = name.clone();
 
@@ -544,9 +554,11 @@ pub fn disable<C>(client: &Client<C>) -> impl Future<Output = Result<AuthChange>
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    let http_client = client.http_client().clone();
+    // A repeated attempt would report the change made by the first one as `Unchanged`.
+    let idempotency = Idempotency::NonIdempotent;
+    let http_client = client.http_client().with_idempotency(&idempotency);
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
 
         async move {
@@ -575,9 +587,11 @@ pub fn enable<C>(client: &Client<C>) -> impl Future<Output = Result<AuthChange>>
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    let http_client = client.http_client().clone();
+    // A repeated attempt would report the change made by the first one as `Unchanged`.
+    let idempotency = Idempotency::NonIdempotent;
+    let http_client = client.http_client().with_idempotency(&idempotency);
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
 
         async move {
@@ -610,7 +624,7 @@ where
     let http_client = client.http_client().clone();
     let name = name.into();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), Idempotency::Idempotent, move |member| {
         let http_client = http_client.clone();
         let name = name.clone();
 
@@ -620,7 +634,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::OK {
                 match serde_json::from_slice::<Role>(&body) {
@@ -641,7 +655,7 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), Idempotency::Idempotent, move |member| {
         let http_client = http_client.clone();
 
         async move {
@@ -649,7 +663,7 @@ where
             let response = http_client.get(uri).await?;
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::OK {
                 match serde_json::from_slice::<Roles>(&body) {
@@ -676,7 +690,7 @@ where
     let http_client = client.http_client().clone();
     let name = name.into();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), Idempotency::Idempotent, move |member| {
         let http_client = http_client.clone();
         let name = name.clone();
         async move {
@@ -685,7 +699,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::OK {
                 match serde_json::from_slice::<UserDetail>(&body) {
@@ -706,7 +720,7 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), Idempotency::Idempotent, move |member| {
         let http_client = http_client.clone();
         async move {
             let uri = build_uri(&member, "/users")?;
@@ -714,7 +728,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::OK {
                 match serde_json::from_slice::<Users>(&body) {
@@ -739,7 +753,7 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), Idempotency::Idempotent, move |member| {
         let http_client = http_client.clone();
         async move {
             let uri = build_uri(&member, "/enable")?;
@@ -747,7 +761,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
             if status == StatusCode::OK {
                 match serde_json::from_slice::<AuthStatus>(&body) {
                     Ok(data) => Ok(Response {
@@ -757,10 +771,7 @@ where
                     Err(error) => Err(Error::Serialization(error)),
                 }
             } else {
-                match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                }
+                Err(Error::from_response(status, cluster_info, &body))
             }
         }
     })
@@ -771,9 +782,11 @@ pub fn update_role<C>(client: &Client<C>, role: RoleUpdate) -> impl Future<Outpu
 where
     C: Clone + Connect + Sync + Send + 'static,
 {
-    let http_client = client.http_client().clone();
+    // Granting or revoking a permission again fails.
+    let idempotency = Idempotency::NonIdempotent;
+    let http_client = client.http_client().with_idempotency(&idempotency);
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
         let role = role.clone();
 
@@ -784,7 +797,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::OK {
                 match serde_json::from_slice::<Role>(&body) {
@@ -802,9 +815,11 @@ pub fn update_user<C>(client: &Client<C>, user: UserUpdate) -> impl Future<Outpu
 where
     C: Clone + Connect + Sync + Send + 'static,
 {
-    let http_client = client.http_client().clone();
+    // Granting or revoking a role again fails.
+    let idempotency = Idempotency::NonIdempotent;
+    let http_client = client.http_client().with_idempotency(&idempotency);
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
         let user = user.clone();
 
@@ -815,7 +830,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::OK {
                 match serde_json::from_slice::<User>(&body) {
@@ -829,7 +844,59 @@ where
     })
 }
 
+/// Checks whether the user or role at the given path is gone, to verify a delete of it.
+fn verify_deleted<C>(client: &Client<C>, path: String) -> Idempotency<()>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    let http_client = client.http_client().clone();
+    let cluster = client.cluster();
+
+    Idempotency::Verifiable(Box::new(move || {
+        let http_client = http_client.clone();
+        let path = path.clone();
+
+        first_ok(cluster.clone(), Idempotency::Idempotent, move |member| {
+            let http_client = http_client.clone();
+            let path = path.clone();
+
+            async move {
+                let uri = build_uri(&member, &path)?;
+                let response = http_client.get(uri).await?;
+
+                let status = response.status();
+                let cluster_info = ClusterInfo::from(response.headers());
+
+                match status {
+                    StatusCode::OK => Ok(Response {
+                        data: false,
+                        cluster_info,
+                    }),
+                    StatusCode::NOT_FOUND => Ok(Response {
+                        data: true,
+                        cluster_info,
+                    }),
+                    status => Err(Error::UnexpectedStatus(status)),
+                }
+            }
+        })
+        .map(|result| {
+            result.map(|response| {
+                if response.data {
+                    Some(Response {
+                        data: (),
+                        cluster_info: response.cluster_info,
+                    })
+                } else {
+                    None
+                }
+            })
+        })
+        .boxed()
+    }))
+}
+
 /// Constructs the full URL for an API call.
 fn build_uri(endpoint: &Uri, path: &str) -> std::result::Result<Uri, http::uri::InvalidUri> {
-    format!("{}v2/auth{}", endpoint, path).parse()
+    build_url(endpoint, &format!("v2/auth{}", path))
 }
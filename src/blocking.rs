@@ -0,0 +1,116 @@
+//! A synchronous facade over the client, for callers that don't run a Tokio runtime.
+//!
+//! `blocking::Client` wraps a `Client` together with a runtime that it owns, and the functions in
+//! the `auth`, `kv`, `members`, and `stats` submodules mirror the functions of the crate's modules
+//! of the same names, blocking the calling thread until the API call has finished. Since they
+//! make the same API calls, they behave the same way, including failing over to other cluster
+//! members, retries, and timeouts, and take and return the same types.
+//!
+//! Calls can be made from several threads at once. Each of them is run on the thread that made it,
+//! while the runtime's worker threads drive the client's connections.
+//!
+//! The functions in this module panic if called from within an asynchronous context, such as a
+//! future running on a Tokio runtime, where the asynchronous functions should be used instead.
+//!
+//! This module requires the `blocking` Cargo feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use etcd::{blocking, ClientBuilder};
+//! use etcd::kv::GetOptions;
+//!
+//! let client = ClientBuilder::new(&["http://etcd.example.com:2379"]).build().unwrap();
+//! let client = blocking::Client::new(client).unwrap();
+//!
+//! blocking::kv::set(&client, "/foo", "bar", None).unwrap();
+//!
+//! let response = blocking::kv::get(&client, "/foo", GetOptions::default()).unwrap();
+//! assert_eq!(response.data.node.value, Some("bar".to_string()));
+//! ```
+
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::stream::StreamExt;
+use hyper::client::connect::Connect;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::client::{Client as AsyncClient, Health, Response};
+use crate::error::Error;
+use crate::version::VersionInfo;
+
+pub mod auth;
+pub mod kv;
+pub mod members;
+pub mod stats;
+
+/// API client for etcd that blocks the calling thread until each API call has finished.
+///
+/// Clones of a client share its connections and runtime, which shuts down once the last clone has
+/// been dropped.
+#[derive(Clone, Debug)]
+pub struct Client<C>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    client: AsyncClient<C>,
+    runtime: Arc<Runtime>,
+}
+
+impl<C> Client<C>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    /// Constructs a new `blocking::Client` that makes API calls with the given client, on a
+    /// runtime of its own.
+    ///
+    /// Fails if the runtime could not be started.
+    pub fn new(client: AsyncClient<C>) -> Result<Self, Error> {
+        let runtime = Builder::new()
+            .threaded_scheduler()
+            .enable_all()
+            .thread_name("etcd-blocking")
+            .build()
+            .map_err(Error::Runtime)?;
+
+        Ok(Client {
+            client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Returns the asynchronous client that makes the API calls, for settings such as
+    /// `Client::set_endpoints`.
+    pub fn async_client(&self) -> &AsyncClient<C> {
+        &self.client
+    }
+
+    /// Runs the given closure in the context of the client's runtime, so that background tasks
+    /// such as `health::spawn_probe` or `discovery::spawn_sync` can be started on it.
+    pub fn enter<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        self.runtime.enter(f)
+    }
+
+    /// Runs a basic health check against each etcd member.
+    pub fn health(&self) -> Vec<Result<Response<Health>, Error>> {
+        self.block_on(self.client.health().collect())
+    }
+
+    /// Returns version information from each of the client's cluster members.
+    pub fn versions(&self) -> Vec<Result<Response<VersionInfo>, Error>> {
+        self.block_on(self.client.versions().collect())
+    }
+
+    /// Runs the given future on the client's runtime, blocking the current thread until it has
+    /// finished.
+    pub(crate) fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        self.runtime.handle().block_on(future)
+    }
+}
@@ -0,0 +1,142 @@
+//! Blocking versions of the functions in the `auth` module.
+
+use hyper::client::connect::Connect;
+
+use crate::auth::{self, AuthChange, NewUser, Role, RoleUpdate, User, UserDetail, UserUpdate};
+use crate::blocking::Client;
+use crate::client::Response;
+use crate::error::Error;
+
+/// Creates a new role.
+///
+/// See `etcd::auth::create_role`.
+pub fn create_role<C>(client: &Client<C>, role: Role) -> Result<Response<Role>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::create_role(client.async_client(), role))
+}
+
+/// Creates a new user.
+///
+/// See `etcd::auth::create_user`.
+pub fn create_user<C>(client: &Client<C>, user: NewUser) -> Result<Response<User>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::create_user(client.async_client(), user))
+}
+
+/// Deletes a role.
+///
+/// See `etcd::auth::delete_role`.
+pub fn delete_role<C, N>(client: &Client<C>, name: N) -> Result<Response<()>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+    N: Into<String>,
+{
+    client.block_on(auth::delete_role(client.async_client(), name))
+}
+
+/// Deletes a user.
+///
+/// See `etcd::auth::delete_user`.
+pub fn delete_user<C, N>(client: &Client<C>, name: N) -> Result<Response<()>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+    N: Into<String>,
+{
+    client.block_on(auth::delete_user(client.async_client(), name))
+}
+
+/// Attempts to disable the auth system.
+///
+/// See `etcd::auth::disable`.
+pub fn disable<C>(client: &Client<C>) -> Result<Response<AuthChange>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::disable(client.async_client()))
+}
+
+/// Attempts to enable the auth system.
+///
+/// See `etcd::auth::enable`.
+pub fn enable<C>(client: &Client<C>) -> Result<Response<AuthChange>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::enable(client.async_client()))
+}
+
+/// Gets a role.
+///
+/// See `etcd::auth::get_role`.
+pub fn get_role<C, N>(client: &Client<C>, name: N) -> Result<Response<Role>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+    N: Into<String>,
+{
+    client.block_on(auth::get_role(client.async_client(), name))
+}
+
+/// Gets all roles.
+///
+/// See `etcd::auth::get_roles`.
+pub fn get_roles<C>(client: &Client<C>) -> Result<Response<Vec<Role>>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::get_roles(client.async_client()))
+}
+
+/// Gets a user.
+///
+/// See `etcd::auth::get_user`.
+pub fn get_user<C, N>(client: &Client<C>, name: N) -> Result<Response<UserDetail>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+    N: Into<String>,
+{
+    client.block_on(auth::get_user(client.async_client(), name))
+}
+
+/// Gets all users.
+///
+/// See `etcd::auth::get_users`.
+pub fn get_users<C>(client: &Client<C>) -> Result<Response<Vec<UserDetail>>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::get_users(client.async_client()))
+}
+
+/// Determines whether or not the auth system is enabled.
+///
+/// See `etcd::auth::status`.
+pub fn status<C>(client: &Client<C>) -> Result<Response<bool>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::status(client.async_client()))
+}
+
+/// Updates an existing role.
+///
+/// See `etcd::auth::update_role`.
+pub fn update_role<C>(client: &Client<C>, role: RoleUpdate) -> Result<Response<Role>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::update_role(client.async_client(), role))
+}
+
+/// Updates an existing user.
+///
+/// See `etcd::auth::update_user`.
+pub fn update_user<C>(client: &Client<C>, user: UserUpdate) -> Result<Response<User>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(auth::update_user(client.async_client(), user))
+}
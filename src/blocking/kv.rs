@@ -0,0 +1,222 @@
+//! Blocking versions of the functions in the `kv` module.
+
+use hyper::client::connect::Connect;
+
+use crate::blocking::Client;
+use crate::client::Response;
+use crate::error::Error;
+use crate::kv::{self, ComparisonConditions, GetOptions, KeyValueInfo, WatchError, WatchOptions};
+
+/// Deletes a node only if the given current value and/or current modified index match.
+///
+/// See `etcd::kv::compare_and_delete`.
+pub fn compare_and_delete<C>(
+    client: &Client<C>,
+    key: &str,
+    current_value: Option<&str>,
+    current_modified_index: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::compare_and_delete(
+        client.async_client(),
+        key,
+        current_value,
+        current_modified_index,
+    ))
+}
+
+/// Updates a node only if the given current value and/or current modified index match.
+///
+/// See `etcd::kv::compare_and_swap`.
+pub fn compare_and_swap<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+    current_value: Option<&str>,
+    current_modified_index: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::compare_and_swap(
+        client.async_client(),
+        key,
+        value,
+        ttl,
+        current_value,
+        current_modified_index,
+    ))
+}
+
+/// Creates a new key-value pair.
+///
+/// See `etcd::kv::create`.
+pub fn create<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::create(client.async_client(), key, value, ttl))
+}
+
+/// Creates a new empty directory.
+///
+/// See `etcd::kv::create_dir`.
+pub fn create_dir<C>(
+    client: &Client<C>,
+    key: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::create_dir(client.async_client(), key, ttl))
+}
+
+/// Creates a new key-value pair in a directory with a numeric key name larger than any of its
+/// previous children.
+///
+/// See `etcd::kv::create_in_order`.
+pub fn create_in_order<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::create_in_order(client.async_client(), key, value, ttl))
+}
+
+/// Deletes a node.
+///
+/// See `etcd::kv::delete`.
+pub fn delete<C>(
+    client: &Client<C>,
+    key: &str,
+    recursive: bool,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::delete(client.async_client(), key, recursive))
+}
+
+/// Deletes an empty directory or a key-value pair at the given key.
+///
+/// See `etcd::kv::delete_dir`.
+pub fn delete_dir<C>(client: &Client<C>, key: &str) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::delete_dir(client.async_client(), key))
+}
+
+/// Gets the value of a node.
+///
+/// See `etcd::kv::get`.
+pub fn get<C>(
+    client: &Client<C>,
+    key: &str,
+    options: GetOptions,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::get(client.async_client(), key, options))
+}
+
+/// Sets the value of a key-value pair.
+///
+/// See `etcd::kv::set`.
+pub fn set<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::set(client.async_client(), key, value, ttl))
+}
+
+/// Refreshes the already set etcd key, bumping its TTL without triggering watcher updates.
+///
+/// See `etcd::kv::refresh`.
+pub fn refresh<C>(
+    client: &Client<C>,
+    key: &str,
+    ttl: u64,
+    conditions: Option<ComparisonConditions<'_>>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::refresh(client.async_client(), key, ttl, conditions))
+}
+
+/// Sets the key to an empty directory.
+///
+/// See `etcd::kv::set_dir`.
+pub fn set_dir<C>(
+    client: &Client<C>,
+    key: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::set_dir(client.async_client(), key, ttl))
+}
+
+/// Updates an existing key-value pair.
+///
+/// See `etcd::kv::update`.
+pub fn update<C>(
+    client: &Client<C>,
+    key: &str,
+    value: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::update(client.async_client(), key, value, ttl))
+}
+
+/// Updates a directory.
+///
+/// See `etcd::kv::update_dir`.
+pub fn update_dir<C>(
+    client: &Client<C>,
+    key: &str,
+    ttl: Option<u64>,
+) -> Result<Response<KeyValueInfo>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::update_dir(client.async_client(), key, ttl))
+}
+
+/// Watches a node for changes and returns the new value as soon as a change takes place.
+///
+/// See `etcd::kv::watch`.
+pub fn watch<C>(
+    client: &Client<C>,
+    key: &str,
+    options: WatchOptions,
+) -> Result<Response<KeyValueInfo>, WatchError>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(kv::watch(client.async_client(), key, options))
+}
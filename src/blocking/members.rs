@@ -0,0 +1,52 @@
+//! Blocking versions of the functions in the `members` module.
+
+use hyper::client::connect::Connect;
+
+use crate::blocking::Client;
+use crate::client::Response;
+use crate::error::Error;
+use crate::members::{self, Member};
+
+/// Adds a new member to the cluster.
+///
+/// See `etcd::members::add`.
+pub fn add<C>(client: &Client<C>, peer_urls: Vec<String>) -> Result<Response<()>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(members::add(client.async_client(), peer_urls))
+}
+
+/// Deletes a member from the cluster.
+///
+/// See `etcd::members::delete`.
+pub fn delete<C>(client: &Client<C>, id: String) -> Result<Response<()>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(members::delete(client.async_client(), id))
+}
+
+/// Lists the members of the cluster.
+///
+/// See `etcd::members::list`.
+pub fn list<C>(client: &Client<C>) -> Result<Response<Vec<Member>>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(members::list(client.async_client()))
+}
+
+/// Updates the peer URLs of a member of the cluster.
+///
+/// See `etcd::members::update`.
+pub fn update<C>(
+    client: &Client<C>,
+    id: String,
+    peer_urls: Vec<String>,
+) -> Result<Response<()>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(members::update(client.async_client(), id, peer_urls))
+}
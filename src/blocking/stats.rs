@@ -0,0 +1,39 @@
+//! Blocking versions of the functions in the `stats` module.
+
+use futures::stream::StreamExt;
+use hyper::client::connect::Connect;
+
+use crate::blocking::Client;
+use crate::client::Response;
+use crate::error::Error;
+use crate::stats::{self, LeaderStats, SelfStats, StoreStats};
+
+/// Returns statistics about the leader member of a cluster.
+///
+/// See `etcd::stats::leader_stats`.
+pub fn leader_stats<C>(client: &Client<C>) -> Result<Response<LeaderStats>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(stats::leader_stats(client.async_client()))
+}
+
+/// Returns statistics about each of the client's cluster members.
+///
+/// See `etcd::stats::self_stats`.
+pub fn self_stats<C>(client: &Client<C>) -> Vec<Result<Response<SelfStats>, Error>>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(stats::self_stats(client.async_client()).collect())
+}
+
+/// Returns statistics about operations handled by each of the client's cluster members.
+///
+/// See `etcd::stats::store_stats`.
+pub fn store_stats<C>(client: &Client<C>) -> Vec<Result<Response<StoreStats>, Error>>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    client.block_on(stats::store_stats(client.async_client()).collect())
+}
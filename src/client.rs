@@ -1,20 +1,44 @@
 //! Contains the etcd client. All API calls are made via the client.
 
 use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
 
 use futures::stream::{self, Stream, StreamExt};
-use http::header::{HeaderMap, HeaderValue};
+use http::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
 use hyper::client::connect::{Connect, HttpConnector};
-use hyper::{Client as Hyper, StatusCode, Uri};
+use hyper::{Body, Client as Hyper, StatusCode, Uri};
 #[cfg(feature = "tls")]
 use hyper_tls::HttpsConnector;
 use log::error;
 use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
-use serde_json;
 
-use crate::error::{ApiError, Error};
+use crate::cluster::Cluster;
+use crate::coalesce::InFlightReads;
+use crate::connector::BoxConnector;
+use crate::credentials::CredentialsProvider;
+use crate::discovery::{srv_endpoints, SrvResolver};
+use crate::endpoint::Endpoint;
+#[cfg(any(feature = "tls", feature = "rustls"))]
+use crate::endpoint::EndpointConnector;
+use crate::error::Error;
+use crate::health::CircuitBreaker;
 use crate::http::HttpClient;
+use crate::middleware::{
+    Auth, Credentials, DefaultHeaders, HttpService, Layer, Layers, Retry, Service, Timeout,
+    Transport,
+};
+#[cfg(feature = "rustls")]
+use crate::rustls::HttpsConnector as RustlsConnector;
+use crate::selector::{EndpointSelector, InOrder, Weighted};
+#[cfg(feature = "tls")]
+use crate::tls::HttpsConnector as TlsConnector;
+#[cfg(any(feature = "tls", feature = "rustls"))]
+use crate::tls::TlsConfig;
+#[cfg(unix)]
+use crate::unix::{self, UnixConnector};
 use crate::version::VersionInfo;
 
 // header! {
@@ -41,18 +65,33 @@ const XRAFT_INDEX: &str = "X-Raft-Index";
 // }
 const XRAFT_TERM: &str = "X-Raft-Term";
 
+/// The default User-Agent HTTP header.
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
 /// API client for etcd.
 ///
 /// All API calls require a client.
+///
+/// The type parameter is the connector type the client was built with. The client makes its
+/// requests through a boxed `middleware::Transport`, so the parameter only records that type and
+/// has no other effect on the client. It is kept so that code naming a client type, such as
+/// `Client<HttpConnector>`, keeps compiling. Code that stores a client should prefer `DynClient`.
 #[derive(Clone, Debug)]
 pub struct Client<C>
 where
     C: Clone + Connect + Sync + Send + 'static,
 {
-    endpoints: Vec<Uri>,
-    http_client: HttpClient<C>,
+    cluster: Arc<Cluster>,
+    connector: PhantomData<C>,
+    http_client: HttpClient,
+    in_flight_reads: Option<InFlightReads>,
 }
 
+/// A client whose connector type is hidden, so that it can be stored without a type parameter.
+///
+/// Any client can be converted into one with `Client::into_dyn`. See the `connector` module.
+pub type DynClient = Client<BoxConnector>;
+
 /// A username and password to use for HTTP basic authentication.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct BasicAuth {
@@ -69,6 +108,518 @@ pub struct Health {
     pub health: String,
 }
 
+/// A builder for configuring a `Client`.
+///
+/// Where the plain constructors on `Client` only take endpoints and credentials, the builder
+/// also controls timeouts, connection reuse, retries, and circuit breaking.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use etcd::ClientBuilder;
+///
+/// let client = ClientBuilder::new(&["http://etcd.example.com:2379"])
+///     .request_timeout(Duration::from_secs(5))
+///     .connect_timeout(Duration::from_secs(1))
+///     .max_retries(2)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    circuit_breaker: Option<CircuitBreaker>,
+    cluster_id: Option<String>,
+    coalesce_reads: bool,
+    connect_timeout: Option<Duration>,
+    credentials: Option<Arc<dyn CredentialsProvider>>,
+    default_headers: HeaderMap,
+    endpoint_selector: Option<Arc<dyn EndpointSelector>>,
+    endpoints: Vec<Endpoint>,
+    layers: Layers,
+    max_response_size: Option<usize>,
+    max_retries: usize,
+    monotonic_reads: bool,
+    outer_layers: Layers,
+    pool_idle_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    retry_backoff: Duration,
+    tcp_keepalive: Option<Duration>,
+}
+
+impl ClientBuilder {
+    /// Constructs a new builder for a client of the given cluster members.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
+    ///   make the call to each member in the order chosen by the endpoint selector until it
+    ///   receives a successful response. An endpoint may include a base path, such as
+    ///   `https://gw.example.com/etcd` for a member behind a reverse proxy.
+    pub fn new(endpoints: &[&str]) -> Self {
+        ClientBuilder {
+            circuit_breaker: None,
+            cluster_id: None,
+            coalesce_reads: false,
+            connect_timeout: None,
+            credentials: None,
+            default_headers: default_headers(),
+            endpoint_selector: None,
+            endpoints: endpoints
+                .iter()
+                .map(|endpoint| Endpoint::new(endpoint))
+                .collect(),
+            layers: Layers::default(),
+            max_response_size: None,
+            max_retries: 0,
+            monotonic_reads: false,
+            outer_layers: Layers::default(),
+            pool_idle_timeout: None,
+            request_timeout: None,
+            retry_backoff: Duration::from_millis(50),
+            tcp_keepalive: None,
+        }
+    }
+
+    /// Constructs a new builder for a client of the given cluster members, each with its own
+    /// settings. See the `endpoint` module.
+    pub fn from_endpoints(endpoints: &[Endpoint]) -> Self {
+        let mut builder = ClientBuilder::new(&[]);
+        builder.endpoints = endpoints.to_vec();
+
+        builder
+    }
+
+    /// Constructs a new builder for a client of the cluster members found in the DNS SRV records
+    /// for the given domain.
+    ///
+    /// Members published under `_etcd-client-ssl._tcp.<domain>` are given HTTPS endpoints, so a
+    /// client for such a cluster must be built with `build_https`. To keep the endpoints up to
+    /// date with the records, use `discovery::spawn_srv_sync`.
+    ///
+    /// # Parameters
+    ///
+    /// * resolver: The resolver to look up the records with.
+    /// * domain: The domain the cluster's records are published under.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records could not be resolved, or `Error::NoEndpoints` if there are none.
+    pub async fn from_srv(resolver: &dyn SrvResolver, domain: &str) -> Result<Self, Error> {
+        let endpoints = srv_endpoints(resolver, domain).await?;
+        let endpoints: Vec<&str> = endpoints.iter().map(String::as_str).collect();
+
+        Ok(ClientBuilder::new(&endpoints))
+    }
+
+    /// Sets the credentials for HTTP basic authentication.
+    ///
+    /// This replaces any credentials provider set with `credentials_provider`.
+    pub fn basic_auth(&mut self, basic_auth: BasicAuth) -> &mut Self {
+        self.credentials = Some(Arc::new(basic_auth));
+        self
+    }
+
+    /// Enables a circuit breaker for each cluster member, so that members that keep failing are
+    /// skipped by API calls until they recover.
+    ///
+    /// See the `health` module for details. By default, every member is tried by every API call.
+    pub fn circuit_breaker(&mut self, circuit_breaker: CircuitBreaker) -> &mut Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Sets the ID of the cluster the client's endpoints are expected to belong to.
+    ///
+    /// Responses from members of any other cluster fail with `Error::ClusterIdMismatch`. By
+    /// default, the client expects the cluster ID of the first response it receives.
+    pub fn cluster_id(&mut self, cluster_id: &str) -> &mut Self {
+        self.cluster_id = Some(cluster_id.to_string());
+        self
+    }
+
+    /// Sets whether identical reads made at the same time are coalesced into a single request.
+    ///
+    /// When many tasks read the same key at once, such as a piece of configuration, each
+    /// `kv::get` makes its own request. With coalesced reads, a `kv::get` that starts while an
+    /// identical one is in flight, from the client or any of its clones, waits for the response
+    /// of that one instead, and every caller gets the same response.
+    ///
+    /// Reads are identical when they have the same key, options, and headers. Quorum reads are
+    /// never coalesced, since a read in flight may have been answered before a write that
+    /// completed when the later read started. A coalesced read that fails returns the same error
+    /// to every caller, wrapped in `Error::Coalesced` if it cannot be copied.
+    ///
+    /// Defaults to false.
+    pub fn coalesce_reads(&mut self, coalesce_reads: bool) -> &mut Self {
+        self.coalesce_reads = coalesce_reads;
+        self
+    }
+
+    /// Sets how long to wait for a TCP connection to a cluster member to be established.
+    ///
+    /// This has no effect on clients built with `build_with_connector`.
+    pub fn connect_timeout(&mut self, connect_timeout: Duration) -> &mut Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Sets the provider the client asks for its credentials, so that they can change while the
+    /// client is in use. See the `credentials` module.
+    ///
+    /// This replaces any credentials set with `basic_auth`.
+    pub fn credentials_provider<P>(&mut self, credentials: Arc<P>) -> &mut Self
+    where
+        P: CredentialsProvider + 'static,
+    {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Adds an HTTP header to every request, such as one a proxy in front of the cluster requires.
+    ///
+    /// Adding a header with the same name again adds another value. A header set on a single API
+    /// call with `Client::with_header` replaces all values of the default header of that name.
+    pub fn default_header(&mut self, name: HeaderName, value: HeaderValue) -> &mut Self {
+        self.default_headers.append(name, value);
+        self
+    }
+
+    /// Sets the strategy for choosing which cluster member receives each API call.
+    ///
+    /// Defaults to `selector::Weighted` if any endpoint has a weight, and to `selector::InOrder`
+    /// otherwise.
+    pub fn endpoint_selector<S>(&mut self, endpoint_selector: S) -> &mut Self
+    where
+        S: EndpointSelector + 'static,
+    {
+        self.endpoint_selector = Some(Arc::new(endpoint_selector));
+        self
+    }
+
+    /// Adds a tower layer around the service that sends the client's HTTP requests, inside the
+    /// client's own layers.
+    ///
+    /// Layers see every attempt of a request to a cluster member. The first layer added is the
+    /// outermost. See the `middleware` module.
+    pub fn layer<L>(&mut self, layer: L) -> &mut Self
+    where
+        L: Layer<Transport> + Send + Sync + 'static,
+        L::Service: Service<http::Request<Body>, Response = http::Response<Body>, Error = Error>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<http::Request<Body>>>::Future: Send + 'static,
+    {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Sets the maximum size in bytes of a response body the client reads. API calls whose
+    /// response is larger fail with `Error::ResponseTooLarge`, without reading the rest of it.
+    ///
+    /// This bounds the memory a single response can take, such as that of a recursive `kv::get`
+    /// of a large directory, or of a misbehaving member. Large directories can still be read with
+    /// `kv::get_nodes`, which only limits the part of the response each node is parsed from.
+    ///
+    /// Defaults to no limit.
+    pub fn max_response_size(&mut self, max_response_size: usize) -> &mut Self {
+        self.max_response_size = Some(max_response_size);
+        self
+    }
+
+    /// Sets how many times a request to a cluster member that fails at the transport level, or
+    /// times out, is retried against that member before moving on to the next one.
+    ///
+    /// Writes that are not idempotent, such as `kv::create_in_order`, `kv::delete`, or a
+    /// compare-and-swap, are never made again if the failed attempt may have reached the member,
+    /// regardless of this setting. They fail with `Error::Indeterminate` instead. Retries wait
+    /// between attempts; see `retry_backoff`.
+    ///
+    /// Defaults to 0.
+    pub fn max_retries(&mut self, max_retries: usize) -> &mut Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets whether reads may return data older than what the client has already observed.
+    ///
+    /// A read that fails over to a member lagging behind the rest of the cluster can return data
+    /// older than a write the same client just made. With monotonic reads, the client remembers
+    /// the highest etcd index it has observed, and a read answered at a lower index is made to the
+    /// next member instead. If every member is behind, the read fails with
+    /// `Error::ClusterUnavailable` containing `Error::StaleRead` errors.
+    ///
+    /// This gives read-your-writes consistency for the client and its clones, without making
+    /// every read a quorum read. It applies to every read that uses the default
+    /// `kv::ReadConsistency::Serializable`, as if it asked for `kv::ReadConsistency::Monotonic`.
+    ///
+    /// Defaults to false.
+    pub fn monotonic_reads(&mut self, monotonic_reads: bool) -> &mut Self {
+        self.monotonic_reads = monotonic_reads;
+        self
+    }
+
+    /// Adds a tower layer around the service that sends the client's HTTP requests, outside the
+    /// client's own layers.
+    ///
+    /// Unlike the layers added with `layer`, these see a request to a cluster member once, before
+    /// its retries, request timeout, default headers, and Authorization header. The first layer
+    /// added is the outermost. See the `middleware` module.
+    pub fn outer_layer<L>(&mut self, layer: L) -> &mut Self
+    where
+        L: Layer<Transport> + Send + Sync + 'static,
+        L::Service: Service<http::Request<Body>, Response = http::Response<Body>, Error = Error>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<http::Request<Body>>>::Future: Send + 'static,
+    {
+        self.outer_layers.push(layer);
+        self
+    }
+
+    /// Sets how long an idle connection to a cluster member is kept open for reuse.
+    pub fn pool_idle_timeout(&mut self, pool_idle_timeout: Duration) -> &mut Self {
+        self.pool_idle_timeout = Some(pool_idle_timeout);
+        self
+    }
+
+    /// Sets how long to wait for a response to each request to a cluster member.
+    ///
+    /// A request whose response, including its body, isn't received by the deadline fails with
+    /// `Error::Timeout`. Watches are not subject to this timeout, since they wait for a change by
+    /// design; use `WatchOptions::timeout` instead. Neither is the stream of `kv::get_nodes` once
+    /// its response has started.
+    pub fn request_timeout(&mut self, request_timeout: Duration) -> &mut Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Sets how long to wait before the first retry of a request to a cluster member. Each later
+    /// retry of the same request waits twice as long as the one before.
+    ///
+    /// Defaults to 50 milliseconds.
+    pub fn retry_backoff(&mut self, retry_backoff: Duration) -> &mut Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Sets the interval of TCP keepalive probes on connections to cluster members.
+    ///
+    /// This has no effect on clients built with `build_with_connector`.
+    pub fn tcp_keepalive(&mut self, tcp_keepalive: Duration) -> &mut Self {
+        self.tcp_keepalive = Some(tcp_keepalive);
+        self
+    }
+
+    /// Sets the User-Agent HTTP header of every request.
+    ///
+    /// Defaults to `etcd/<crate version>`.
+    pub fn user_agent(&mut self, user_agent: HeaderValue) -> &mut Self {
+        self.default_headers.insert(USER_AGENT, user_agent);
+        self
+    }
+
+    /// Constructs a new client using the HTTP protocol.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    pub fn build(&self) -> Result<Client<HttpConnector>, Error> {
+        self.build_with_connector(self.http_connector())
+    }
+
+    /// Constructs a new client using the HTTPS protocol.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    #[cfg(feature = "tls")]
+    pub fn build_https(&self) -> Result<Client<HttpsConnector<HttpConnector>>, Error> {
+        let mut http_connector = self.http_connector();
+        http_connector.enforce_http(false);
+
+        self.build_with_connector(HttpsConnector::new_with_connector(http_connector))
+    }
+
+    /// Constructs a new client using the HTTPS protocol with the given TLS configuration.
+    ///
+    /// If the client's credentials provider supplies a TLS configuration, it is used instead of
+    /// the given one, and picked up again for each new connection.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided, if any of the endpoints is an invalid URL, or if the
+    /// TLS configuration is invalid.
+    #[cfg(feature = "tls")]
+    pub fn build_https_with_config(
+        &self,
+        config: &TlsConfig,
+    ) -> Result<Client<TlsConnector>, Error> {
+        let connector = TlsConnector::new(self.http_connector(), config, self.credentials.clone())?;
+
+        self.build_with_connector(connector)
+    }
+
+    /// Constructs a new client using the HTTPS protocol with the given TLS configuration, with
+    /// rustls instead of the platform's native TLS library.
+    ///
+    /// If the client's credentials provider supplies a TLS configuration, it is used instead of
+    /// the given one, and picked up again for each new connection.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided, if any of the endpoints is an invalid URL, or if the
+    /// TLS configuration is invalid.
+    #[cfg(feature = "rustls")]
+    pub fn build_rustls(&self, config: &TlsConfig) -> Result<Client<RustlsConnector>, Error> {
+        let connector =
+            RustlsConnector::new(self.http_connector(), config, self.credentials.clone())?;
+
+        self.build_with_connector(connector)
+    }
+
+    /// Constructs a new client that connects to each cluster member with the TLS configuration
+    /// of its `Endpoint`, over HTTPS or plain HTTP as its URL says.
+    ///
+    /// HTTPS endpoints without their own TLS configuration use the default one.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided, if any of the endpoints is an invalid URL, or if any
+    /// TLS configuration is invalid.
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    pub fn build_per_endpoint(&self) -> Result<Client<EndpointConnector>, Error> {
+        let connector = EndpointConnector::new(
+            self.http_connector(),
+            &self.endpoints,
+            self.credentials.clone(),
+        )?;
+
+        self.build_with_connector(connector)
+    }
+
+    /// Constructs a new client for cluster members listening on Unix domain sockets.
+    ///
+    /// See the `unix` module for the form of the endpoints.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    #[cfg(unix)]
+    pub fn build_unix(&self) -> Result<Client<UnixConnector>, Error> {
+        self.build_with_connector(UnixConnector::new())
+    }
+
+    /// Constructs a new client using the provided connector.
+    ///
+    /// This is necessary when using a custom transport. For X.509 client certificate
+    /// authentication, `build_https_with_config` is usually enough.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    pub fn build_with_connector<C>(&self, connector: C) -> Result<Client<C>, Error>
+    where
+        C: Clone + Connect + Sync + Send + 'static,
+    {
+        let mut hyper = Hyper::builder();
+
+        if let Some(pool_idle_timeout) = self.pool_idle_timeout {
+            hyper.pool_idle_timeout(pool_idle_timeout);
+        }
+
+        if self.endpoints.is_empty() {
+            return Err(Error::NoEndpoints);
+        }
+
+        let endpoints = self
+            .endpoints
+            .iter()
+            .map(Endpoint::uri)
+            .collect::<Result<Vec<Uri>, Error>>()?;
+
+        let endpoint_credentials = endpoints
+            .iter()
+            .zip(&self.endpoints)
+            .filter_map(|(uri, endpoint)| {
+                let credentials = endpoint.credentials.clone()?;
+
+                Some((uri.to_string(), credentials))
+            })
+            .collect();
+
+        let cluster = Cluster::new(
+            endpoints.clone(),
+            self.selector_for(&endpoints),
+            self.circuit_breaker,
+            self.cluster_id.clone(),
+            self.monotonic_reads,
+        );
+
+        let transport = self
+            .layers
+            .apply(Transport::new(HttpService::new(hyper.build(connector))));
+        let transport = DefaultHeaders::new(transport, self.default_headers.clone());
+        let credentials = Credentials::new(self.credentials.clone(), endpoint_credentials);
+        let transport = Retry::new(
+            Timeout::new(Auth::new(transport, credentials), self.request_timeout),
+            self.max_retries,
+            self.retry_backoff,
+        );
+
+        Ok(Client {
+            cluster: Arc::new(cluster),
+            connector: PhantomData,
+            http_client: HttpClient::new(
+                self.outer_layers.apply(Transport::new(transport)),
+                self.max_response_size,
+            ),
+            in_flight_reads: if self.coalesce_reads {
+                Some(InFlightReads::default())
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Returns the chosen endpoint selector, or the default one for the endpoints.
+    fn selector_for(&self, endpoints: &[Uri]) -> Arc<dyn EndpointSelector> {
+        if let Some(ref endpoint_selector) = self.endpoint_selector {
+            return endpoint_selector.clone();
+        }
+
+        let mut weighted = Weighted::new();
+        let mut has_weights = false;
+
+        for (uri, endpoint) in endpoints.iter().zip(&self.endpoints) {
+            if let Some(weight) = endpoint.weight {
+                weighted.weight(uri.clone(), weight);
+                has_weights = true;
+            }
+        }
+
+        if has_weights {
+            Arc::new(weighted)
+        } else {
+            Arc::new(InOrder)
+        }
+    }
+
+    /// Constructs the `HttpConnector` used by `build` and `build_https`.
+    fn http_connector(&self) -> HttpConnector {
+        let mut http_connector = HttpConnector::new();
+        http_connector.set_connect_timeout(self.connect_timeout);
+        http_connector.set_keepalive(self.tcp_keepalive);
+
+        http_connector
+    }
+}
+
 impl Client<HttpConnector> {
     /// Constructs a new client using the HTTP protocol.
     ///
@@ -76,7 +627,7 @@ impl Client<HttpConnector> {
     ///
     /// * handle: A handle to the event loop.
     /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
-    /// make the call to each member in order until it receives a successful respponse.
+    ///   make the call to each member in order until it receives a successful respponse.
     /// * basic_auth: Credentials for HTTP basic authentication.
     ///
     /// # Errors
@@ -100,7 +651,7 @@ impl Client<HttpsConnector<HttpConnector>> {
     ///
     /// * handle: A handle to the event loop.
     /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
-    /// make the call to each member in order until it receives a successful respponse.
+    ///   make the call to each member in order until it receives a successful respponse.
     /// * basic_auth: Credentials for HTTP basic authentication.
     ///
     /// # Errors
@@ -115,6 +666,139 @@ impl Client<HttpsConnector<HttpConnector>> {
 
         Client::custom(hyper, endpoints, basic_auth)
     }
+
+    /// Constructs a new client for the cluster members found in the DNS SRV records for the given
+    /// domain.
+    ///
+    /// The records may point at members serving HTTP as well as HTTPS, so the client supports
+    /// both protocols. To keep the endpoints up to date with the records, use
+    /// `discovery::spawn_srv_sync`.
+    ///
+    /// # Parameters
+    ///
+    /// * resolver: The resolver to look up the records with.
+    /// * domain: The domain the cluster's records are published under.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the records could not be resolved, or `Error::NoEndpoints` if there are none.
+    pub async fn from_srv(
+        resolver: &dyn SrvResolver,
+        domain: &str,
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Client<HttpsConnector<HttpConnector>>, Error> {
+        let mut builder = ClientBuilder::from_srv(resolver, domain).await?;
+
+        if let Some(basic_auth) = basic_auth {
+            builder.basic_auth(basic_auth);
+        }
+
+        builder.build_https()
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Client<TlsConnector> {
+    /// Constructs a new client using the HTTPS protocol with the given TLS configuration.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
+    ///   make the call to each member in order until it receives a successful response.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    /// * config: The TLS configuration, such as the CA bundle to verify servers against and the
+    ///   client certificate to present.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided, if any of the endpoints is an invalid URL, or if the
+    /// TLS configuration is invalid.
+    pub fn https_with_config(
+        endpoints: &[&str],
+        basic_auth: Option<BasicAuth>,
+        config: &TlsConfig,
+    ) -> Result<Client<TlsConnector>, Error> {
+        let connector = TlsConnector::new(HttpConnector::new(), config, None)?;
+        let hyper = Hyper::builder().build(connector);
+
+        Client::custom(hyper, endpoints, basic_auth)
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl Client<RustlsConnector> {
+    /// Constructs a new client using the HTTPS protocol, with rustls instead of the platform's
+    /// native TLS library.
+    ///
+    /// Servers are verified against the Mozilla root certificates bundled with the crate. To use
+    /// other CA certificates or a client certificate, use `Client::https_rustls_with_config`.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
+    ///   make the call to each member in order until it receives a successful response.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    pub fn https_rustls(
+        endpoints: &[&str],
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Client<RustlsConnector>, Error> {
+        Client::https_rustls_with_config(endpoints, basic_auth, &TlsConfig::new())
+    }
+
+    /// Constructs a new client using the HTTPS protocol, with rustls and the given TLS
+    /// configuration.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
+    ///   make the call to each member in order until it receives a successful response.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    /// * config: The TLS configuration, such as the CA bundle to verify servers against and the
+    ///   client certificate to present.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided, if any of the endpoints is an invalid URL, or if the
+    /// TLS configuration is invalid.
+    pub fn https_rustls_with_config(
+        endpoints: &[&str],
+        basic_auth: Option<BasicAuth>,
+        config: &TlsConfig,
+    ) -> Result<Client<RustlsConnector>, Error> {
+        let connector = RustlsConnector::new(HttpConnector::new(), config, None)?;
+        let hyper = Hyper::builder().build(connector);
+
+        Client::custom(hyper, endpoints, basic_auth)
+    }
+}
+
+#[cfg(unix)]
+impl Client<UnixConnector> {
+    /// Constructs a new client for cluster members listening on Unix domain sockets.
+    ///
+    /// # Parameters
+    ///
+    /// * endpoints: URLs for one or more cluster members, of the form `unix:///path/to/socket`.
+    ///   When making an API call, the client will make the call to each member in order until it
+    ///   receives a successful response.
+    /// * basic_auth: Credentials for HTTP basic authentication.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL.
+    pub fn unix(
+        endpoints: &[&str],
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Client<UnixConnector>, Error> {
+        let hyper = Hyper::builder().build(UnixConnector::new());
+
+        Client::custom(hyper, endpoints, basic_auth)
+    }
 }
 
 impl<C> Client<C>
@@ -124,13 +808,14 @@ where
     /// Constructs a new client using the provided `hyper::Client`.
     ///
     /// This method allows the user to configure the details of the underlying HTTP client to their
-    /// liking. It is also necessary when using X.509 client certificate authentication.
+    /// liking. For X.509 client certificate authentication, `Client::https_with_config` is usually
+    /// enough.
     ///
     /// # Parameters
     ///
     /// * hyper: A fully configured `hyper::Client`.
     /// * endpoints: URLs for one or more cluster members. When making an API call, the client will
-    /// make the call to each member in order until it receives a successful respponse.
+    ///   make the call to each member in order until it receives a successful respponse.
     /// * basic_auth: Credentials for HTTP basic authentication.
     ///
     /// # Errors
@@ -141,50 +826,187 @@ where
         endpoints: &[&str],
         basic_auth: Option<BasicAuth>,
     ) -> Result<Client<C>, Error> {
-        if endpoints.len() < 1 {
-            return Err(Error::NoEndpoints);
-        }
-
-        let mut uri_endpoints = Vec::with_capacity(endpoints.len());
-
-        for endpoint in endpoints {
-            uri_endpoints.push(endpoint.parse()?);
-        }
+        let cluster = Cluster::new(
+            parse_endpoints(endpoints)?,
+            Arc::new(InOrder),
+            None,
+            None,
+            false,
+        );
+        let credentials = Credentials::new(
+            basic_auth.map(|basic_auth| Arc::new(basic_auth) as Arc<dyn CredentialsProvider>),
+            Vec::new(),
+        );
+        let transport = Auth::new(
+            DefaultHeaders::new(HttpService::new(hyper), default_headers()),
+            credentials,
+        );
 
         Ok(Client {
-            endpoints: uri_endpoints,
-            http_client: HttpClient::new(hyper, basic_auth),
+            cluster: Arc::new(cluster),
+            connector: PhantomData,
+            http_client: HttpClient::new(Transport::new(transport), None),
+            in_flight_reads: None,
         })
     }
 
     /// Lets other internal code access the `HttpClient`.
-    pub(crate) fn http_client(&self) -> &HttpClient<C> {
+    pub(crate) fn http_client(&self) -> &HttpClient {
         &self.http_client
     }
 
-    /// Lets other internal code access the cluster endpoints.
-    pub(crate) fn endpoints(&self) -> &[Uri] {
-        &self.endpoints
+    /// Lets other internal code access the reads in flight, if the client coalesces reads.
+    pub(crate) fn in_flight_reads(&self) -> Option<&InFlightReads> {
+        self.in_flight_reads.as_ref()
+    }
+
+    /// Lets other internal code access the shared state about the cluster members.
+    pub(crate) fn cluster(&self) -> Arc<Cluster> {
+        self.cluster.clone()
+    }
+
+    /// Converts the client into a `DynClient`, which hides its connector type.
+    ///
+    /// The client keeps making API calls exactly as before, and shares its connections and its
+    /// state about the cluster members with any clones of the original client. Since the
+    /// connector type is only recorded in the client's type, the conversion boxes nothing.
+    pub fn into_dyn(self) -> DynClient {
+        Client {
+            cluster: self.cluster,
+            connector: PhantomData,
+            http_client: self.http_client,
+            in_flight_reads: self.in_flight_reads,
+        }
+    }
+
+    /// Returns a copy of the client that sets the given HTTP header on the requests of its API
+    /// calls, replacing any default header of the same name.
+    ///
+    /// The copy shares everything else with the client, such as the state about the cluster
+    /// members, so it is cheap to make one for a single API call:
+    ///
+    /// ```no_run
+    /// # use etcd::Client;
+    /// # use etcd::kv::{self, GetOptions};
+    /// # use hyper::header::{HeaderName, HeaderValue};
+    /// # async fn example(client: Client<hyper::client::HttpConnector>) {
+    /// let tenant = HeaderName::from_static("x-tenant");
+    /// let client = client.with_header(tenant, HeaderValue::from_static("billing"));
+    ///
+    /// kv::get(&client, "/foo", GetOptions::default()).await.unwrap();
+    /// # }
+    /// ```
+    pub fn with_header(&self, name: HeaderName, value: HeaderValue) -> Self {
+        Client {
+            cluster: self.cluster.clone(),
+            connector: PhantomData,
+            http_client: self.http_client.with_header(name, value),
+            in_flight_reads: self.in_flight_reads.clone(),
+        }
+    }
+
+    /// Returns the URLs of the cluster members the client currently makes API calls to.
+    ///
+    /// These are the endpoints the client was constructed with, unless they have since been
+    /// changed with `set_endpoints`, `add_endpoint`, `remove_endpoint`, or the `discovery`
+    /// module. Endpoints on Unix domain sockets are returned in the `unix:///path/to/socket` form
+    /// they were given in.
+    pub fn endpoints(&self) -> Vec<String> {
+        self.cluster
+            .endpoints()
+            .iter()
+            .map(display_endpoint)
+            .collect()
+    }
+
+    /// Returns the ID of the cluster the client's endpoints are expected to belong to.
+    ///
+    /// This is the ID the client was configured with, or else the cluster ID of the first response
+    /// it received. If neither exists yet, returns `None`.
+    pub fn cluster_id(&self) -> Option<String> {
+        self.cluster.cluster_id()
+    }
+
+    /// Returns the highest etcd index the client or any of its clones has observed in a response
+    /// to an API call, if any.
+    pub fn etcd_index(&self) -> Option<u64> {
+        self.cluster.etcd_index()
+    }
+
+    /// Replaces the URLs of the cluster members the client makes API calls to.
+    ///
+    /// The change applies to all clones of the client. API calls that are already in progress
+    /// keep using the endpoints they started with.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no endpoints are provided or if any of the endpoints is an invalid URL, in which
+    /// case the endpoints are left unchanged.
+    pub fn set_endpoints(&self, endpoints: &[&str]) -> Result<(), Error> {
+        self.cluster.set_endpoints(parse_endpoints(endpoints)?);
+
+        Ok(())
+    }
+
+    /// Adds the URL of a cluster member to the endpoints the client makes API calls to, unless
+    /// it is already one of them.
+    ///
+    /// The change applies to all clones of the client. API calls that are already in progress
+    /// keep using the endpoints they started with.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the endpoint is an invalid URL.
+    pub fn add_endpoint(&self, endpoint: &str) -> Result<(), Error> {
+        let endpoint = parse_endpoint(endpoint)?;
+
+        self.cluster.update_endpoints(|endpoints| {
+            if !endpoints.contains(&endpoint) {
+                endpoints.push(endpoint);
+            }
+        })
+    }
+
+    /// Removes the URL of a cluster member from the endpoints the client makes API calls to.
+    ///
+    /// The change applies to all clones of the client. API calls that are already in progress
+    /// keep using the endpoints they started with.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the endpoint is an invalid URL, or with `Error::NoEndpoints` if it is the only
+    /// endpoint left, in which case the endpoints are left unchanged.
+    pub fn remove_endpoint(&self, endpoint: &str) -> Result<(), Error> {
+        let endpoint = parse_endpoint(endpoint)?;
+
+        self.cluster
+            .update_endpoints(|endpoints| endpoints.retain(|existing| *existing != endpoint))
     }
 
     /// Runs a basic health check against each etcd member.
     pub fn health<'a>(&'a self) -> impl Stream<Item = Result<Response<Health>, Error>> + 'a {
-        stream::iter(self.endpoints.clone())
+        let endpoints = self.cluster.endpoints().to_vec();
+        let concurrency = endpoints.len();
+
+        stream::iter(endpoints)
             .map(move |endpoint| async move {
                 let uri = build_url(&endpoint, "health")?;
                 self.request(uri).await
             })
-            .buffer_unordered(self.endpoints.len())
+            .buffer_unordered(concurrency)
     }
 
-    /// Returns version information from each etcd cluster member the client was initialized with.
+    /// Returns version information from each of the client's cluster members.
     pub fn versions<'a>(&'a self) -> impl Stream<Item = Result<Response<VersionInfo>, Error>> + 'a {
-        stream::iter(self.endpoints.clone())
+        let endpoints = self.cluster.endpoints().to_vec();
+        let concurrency = endpoints.len();
+
+        stream::iter(endpoints)
             .map(move |endpoint| async move {
                 let uri = build_url(&endpoint, "version")?;
                 self.request(uri).await
             })
-            .buffer_unordered(self.endpoints.len())
+            .buffer_unordered(concurrency)
     }
 
     /// Lets other internal code make basic HTTP requests.
@@ -192,23 +1014,22 @@ where
     where
         T: DeserializeOwned + Send + 'static,
     {
+        let cluster = self.cluster.clone();
         let http_client = self.http_client.clone();
 
         async move {
             let response = http_client.get(uri).await?;
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            cluster.check_cluster_id(cluster_info.cluster_id.as_deref())?;
+            let body = http_client.read_body(response).await?;
             if status == StatusCode::OK {
                 match serde_json::from_slice::<T>(&body) {
                     Ok(data) => Ok(Response { data, cluster_info }),
                     Err(error) => Err(Error::Serialization(error)),
                 }
             } else {
-                match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                }
+                Err(Error::from_response(status, cluster_info, &body))
             }
         }
     }
@@ -291,15 +1112,82 @@ impl<'a> From<&'a HeaderMap<HeaderValue>> for ClusterInfo {
         });
 
         ClusterInfo {
-            cluster_id: cluster_id,
-            etcd_index: etcd_index,
-            raft_index: raft_index,
-            raft_term: raft_term,
+            cluster_id,
+            etcd_index,
+            raft_index,
+            raft_term,
         }
     }
 }
 
-/// Constructs the full URL for the versions API call.
-fn build_url(endpoint: &Uri, path: &str) -> Result<Uri, http::uri::InvalidUri> {
-    format!("{}{}", endpoint, path).parse()
+/// Returns the HTTP headers every request has unless configured otherwise.
+fn default_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
+
+    headers
+}
+
+/// Parses the URLs of cluster members.
+fn parse_endpoints(endpoints: &[&str]) -> Result<Vec<Uri>, Error> {
+    if endpoints.is_empty() {
+        return Err(Error::NoEndpoints);
+    }
+
+    let mut uri_endpoints = Vec::with_capacity(endpoints.len());
+
+    for endpoint in endpoints {
+        uri_endpoints.push(parse_endpoint(endpoint)?);
+    }
+
+    Ok(uri_endpoints)
+}
+
+/// Parses the URL of a cluster member.
+pub(crate) fn parse_endpoint(endpoint: &str) -> Result<Uri, Error> {
+    #[cfg(unix)]
+    {
+        if let Some(endpoint) = unix::encode_endpoint(endpoint) {
+            return Ok(endpoint.parse()?);
+        }
+    }
+
+    Ok(endpoint.parse()?)
+}
+
+/// Returns the URL of a cluster member as it is shown to users, which for a member on a Unix
+/// domain socket is the `unix:///path/to/socket` form it was given in.
+pub(crate) fn display_endpoint(endpoint: &Uri) -> String {
+    #[cfg(unix)]
+    {
+        if let Some(endpoint) = unix::decode_endpoint(endpoint) {
+            return endpoint;
+        }
+    }
+
+    endpoint.to_string()
+}
+
+/// Constructs the full URL for an API call by appending the API path, such as `v2/keys/foo`, to
+/// the path of a cluster member's endpoint.
+///
+/// The endpoint's path is treated as a directory whether or not it ends with a slash, so both
+/// `https://gw.example.com/etcd` and `https://gw.example.com/etcd/` give
+/// `https://gw.example.com/etcd/v2/keys/foo`. Any query string in the endpoint is dropped.
+pub(crate) fn build_url(endpoint: &Uri, path: &str) -> Result<Uri, http::uri::InvalidUri> {
+    let scheme = endpoint.scheme_str().unwrap_or("http");
+    let authority = endpoint
+        .authority()
+        .map(|authority| authority.as_str())
+        .unwrap_or("");
+    let base_path = endpoint.path().trim_end_matches('/');
+
+    format!(
+        "{}://{}{}/{}",
+        scheme,
+        authority,
+        base_path,
+        path.trim_start_matches('/')
+    )
+    .parse()
 }
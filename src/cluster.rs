@@ -0,0 +1,252 @@
+//! Shared state about the members of the etcd cluster a client talks to.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use hyper::Uri;
+
+use crate::error::Error;
+use crate::health::{Breaker, CircuitBreaker, EndpointState};
+use crate::selector::EndpointSelector;
+
+/// The cluster members a client makes API calls to, and how it chooses between them.
+#[derive(Debug)]
+pub struct Cluster {
+    breakers: Mutex<HashMap<Uri, Breaker>>,
+    circuit_breaker: Option<CircuitBreaker>,
+    cluster_id: Mutex<Option<String>>,
+    endpoint_selector: Arc<dyn EndpointSelector>,
+    endpoints: RwLock<Arc<Vec<Uri>>>,
+    etcd_index: Mutex<Option<u64>>,
+    monotonic_reads: bool,
+}
+
+impl Cluster {
+    /// Constructs a new `Cluster`.
+    pub fn new(
+        endpoints: Vec<Uri>,
+        endpoint_selector: Arc<dyn EndpointSelector>,
+        circuit_breaker: Option<CircuitBreaker>,
+        cluster_id: Option<String>,
+        monotonic_reads: bool,
+    ) -> Self {
+        Cluster {
+            breakers: Mutex::new(HashMap::new()),
+            circuit_breaker,
+            cluster_id: Mutex::new(cluster_id),
+            endpoint_selector,
+            endpoints: RwLock::new(Arc::new(endpoints)),
+            etcd_index: Mutex::new(None),
+            monotonic_reads,
+        }
+    }
+
+    /// Returns the ID of the cluster, if it is known yet.
+    pub fn cluster_id(&self) -> Option<String> {
+        self.cluster_id.lock().unwrap().clone()
+    }
+
+    /// Checks that a response, successful or not, came from the expected cluster.
+    ///
+    /// If the ID of the cluster is not known yet, the response's cluster ID becomes the expected
+    /// one. Responses without a cluster ID are accepted.
+    pub fn check_cluster_id(&self, cluster_id: Option<&str>) -> Result<(), Error> {
+        let actual = match cluster_id {
+            Some(actual) => actual,
+            None => return Ok(()),
+        };
+
+        let mut expected = self.cluster_id.lock().unwrap();
+
+        match *expected {
+            Some(ref expected) if expected != actual => Err(Error::ClusterIdMismatch {
+                expected: expected.clone(),
+                actual: actual.to_owned(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                *expected = Some(actual.to_owned());
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the current endpoints of the cluster members.
+    ///
+    /// The returned list is a snapshot, which is unaffected by later changes to the endpoints.
+    pub fn endpoints(&self) -> Arc<Vec<Uri>> {
+        self.endpoints.read().unwrap().clone()
+    }
+
+    /// Returns the endpoints to try for a single API call, in order.
+    ///
+    /// Endpoints whose circuit breaker is open are left out, unless that would leave none.
+    pub fn endpoints_to_try(&self) -> Vec<Uri> {
+        let endpoints = self.endpoint_selector.order(&self.endpoints());
+
+        let config = match self.circuit_breaker {
+            Some(ref config) => config,
+            None => return endpoints,
+        };
+
+        let breakers = self.breakers.lock().unwrap();
+        let available: Vec<Uri> = endpoints
+            .iter()
+            .filter(|endpoint| {
+                breakers
+                    .get(endpoint)
+                    .map_or(true, |breaker| !breaker.is_open(config))
+            })
+            .cloned()
+            .collect();
+
+        if available.is_empty() {
+            endpoints
+        } else {
+            available
+        }
+    }
+
+    /// Returns whether an API call may be made to an endpoint returned by `endpoints_to_try` now
+    /// that it is reached, which uses up the trial call of a half-open circuit breaker.
+    ///
+    /// An endpoint whose breaker is open is still allowed if every endpoint's breaker is open.
+    pub fn allow(&self, endpoint: &Uri) -> bool {
+        let config = match self.circuit_breaker {
+            Some(ref config) => config,
+            None => return true,
+        };
+
+        // Changing the endpoints locks the breakers while holding the endpoints' lock, so the
+        // endpoints are read before the breakers are locked rather than while they are.
+        let endpoints = self.endpoints();
+        let mut breakers = self.breakers.lock().unwrap();
+
+        if breakers
+            .entry(endpoint.clone())
+            .or_insert_with(Breaker::new)
+            .allow(config)
+        {
+            return true;
+        }
+
+        endpoints.iter().all(|endpoint| {
+            breakers
+                .get(endpoint)
+                .map_or(false, |breaker| breaker.is_open(config))
+        })
+    }
+
+    /// Returns the circuit breaker state of each endpoint.
+    pub fn endpoint_states(&self) -> Vec<(Uri, EndpointState)> {
+        // Changing the endpoints locks the breakers while holding the endpoints' lock, so the
+        // endpoints are read before the breakers are locked rather than while they are.
+        let endpoints = self.endpoints();
+        let breakers = self.breakers.lock().unwrap();
+
+        endpoints
+            .iter()
+            .map(|endpoint| {
+                let state = match (self.circuit_breaker, breakers.get(endpoint)) {
+                    (Some(ref config), Some(breaker)) => breaker.state(config),
+                    _ => EndpointState::Closed,
+                };
+
+                (endpoint.clone(), state)
+            })
+            .collect()
+    }
+
+    /// Replaces the endpoints of the cluster members.
+    ///
+    /// API calls that are already in progress keep using the endpoints they started with.
+    pub fn set_endpoints(&self, endpoints: Vec<Uri>) {
+        let mut current = self.endpoints.write().unwrap();
+        self.forget_removed(&endpoints);
+        *current = Arc::new(endpoints);
+    }
+
+    /// Changes the endpoints of the cluster members with the given function, as a single update
+    /// with respect to other changes.
+    ///
+    /// Fails with `Error::NoEndpoints`, leaving the endpoints unchanged, if no endpoints would be
+    /// left.
+    pub fn update_endpoints<F>(&self, update: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Vec<Uri>),
+    {
+        let mut endpoints = self.endpoints.write().unwrap();
+        let mut updated = endpoints.to_vec();
+        update(&mut updated);
+
+        if updated.is_empty() {
+            return Err(Error::NoEndpoints);
+        }
+
+        self.forget_removed(&updated);
+        *endpoints = Arc::new(updated);
+
+        Ok(())
+    }
+
+    /// Returns the highest etcd index observed in any response, if any.
+    pub fn etcd_index(&self) -> Option<u64> {
+        *self.etcd_index.lock().unwrap()
+    }
+
+    /// Returns whether reads that don't ask for a consistency level have to be monotonic.
+    pub fn monotonic_reads(&self) -> bool {
+        self.monotonic_reads
+    }
+
+    /// Records the etcd index of a response.
+    pub fn observe_etcd_index(&self, index: u64) {
+        let mut etcd_index = self.etcd_index.lock().unwrap();
+
+        match *etcd_index {
+            Some(current) if current >= index => {}
+            _ => *etcd_index = Some(index),
+        }
+    }
+
+    /// Records that an endpoint answered an API call after the given amount of time.
+    pub fn succeeded(&self, endpoint: &Uri, latency: Duration) {
+        self.endpoint_selector.succeeded(endpoint, latency);
+        self.record_health(endpoint, true);
+    }
+
+    /// Records that an endpoint could not answer an API call.
+    pub fn failed(&self, endpoint: &Uri) {
+        self.endpoint_selector.failed(endpoint);
+        self.record_health(endpoint, false);
+    }
+
+    /// Updates the circuit breaker of an endpoint with the outcome of an API call or health check.
+    pub fn record_health(&self, endpoint: &Uri, healthy: bool) {
+        let config = match self.circuit_breaker {
+            Some(ref config) => config,
+            None => return,
+        };
+
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers
+            .entry(endpoint.clone())
+            .or_insert_with(Breaker::new);
+
+        if healthy {
+            breaker.succeeded();
+        } else {
+            breaker.failed(config);
+        }
+    }
+
+    /// Drops the circuit breakers of endpoints that are no longer in the given list.
+    fn forget_removed(&self, endpoints: &[Uri]) {
+        self.breakers
+            .lock()
+            .unwrap()
+            .retain(|endpoint, _| endpoints.contains(endpoint));
+    }
+}
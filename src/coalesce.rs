@@ -0,0 +1,136 @@
+//! Coalescing of identical reads made at the same time into a single request.
+//!
+//! A read that starts while an identical one is in flight waits for the response of that read
+//! instead of making its own request. See `ClientBuilder::coalesce_reads`.
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use futures::future::{BoxFuture, FutureExt, Shared, WeakShared};
+use http::header::HeaderMap;
+
+use crate::client::Response;
+use crate::error::Error;
+use crate::kv::KeyValueInfo;
+
+/// The result of a read in flight.
+type SharedResult = Result<Response<KeyValueInfo>, Arc<Error>>;
+
+/// A read in flight, whose result is shared by every caller waiting for it.
+type SharedRead = Shared<BoxFuture<'static, SharedResult>>;
+
+/// What makes two reads identical: the key, the query parameters and headers of the request, and
+/// the lowest etcd index a monotonic read accepts.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct ReadKey {
+    headers: Vec<(String, Vec<u8>)>,
+    key: String,
+    min_index: Option<u64>,
+    query_pairs: Vec<(&'static str, String)>,
+}
+
+impl ReadKey {
+    /// Constructs the key of a read with the given parameters.
+    pub fn new(
+        key: &str,
+        query_pairs: &HashMap<&'static str, String>,
+        headers: &HeaderMap,
+        min_index: Option<u64>,
+    ) -> Self {
+        let mut headers: Vec<_> = headers
+            .iter()
+            .map(|(name, value)| (name.as_str().to_owned(), value.as_bytes().to_vec()))
+            .collect();
+        headers.sort();
+
+        let mut query_pairs: Vec<_> = query_pairs
+            .iter()
+            .map(|(name, value)| (*name, value.clone()))
+            .collect();
+        query_pairs.sort();
+
+        ReadKey {
+            headers,
+            key: key.to_owned(),
+            min_index,
+            query_pairs,
+        }
+    }
+}
+
+/// The reads of a client and its clones that are currently in flight.
+#[derive(Clone, Default)]
+pub(crate) struct InFlightReads {
+    reads: Arc<Mutex<HashMap<ReadKey, WeakShared<BoxFuture<'static, SharedResult>>>>>,
+}
+
+impl InFlightReads {
+    /// Waits for the identical read in flight, or makes the read with the given future if there is
+    /// none.
+    ///
+    /// The request is cancelled if every caller waiting for it is dropped.
+    pub fn read<F>(
+        &self,
+        key: ReadKey,
+        read: F,
+    ) -> impl Future<Output = Result<Response<KeyValueInfo>, Error>>
+    where
+        F: Future<Output = Result<Response<KeyValueInfo>, Error>> + Send + 'static,
+    {
+        let shared = self.join_or_start(key, read);
+
+        async move {
+            shared.await.map_err(|error| {
+                // Only the last caller to get the result can take the error instead of a copy.
+                Arc::try_unwrap(error)
+                    .unwrap_or_else(|error| error.try_clone().unwrap_or(Error::Coalesced(error)))
+            })
+        }
+    }
+
+    // private
+
+    /// Returns the identical read in flight, or starts the given one.
+    fn join_or_start<F>(&self, key: ReadKey, read: F) -> SharedRead
+    where
+        F: Future<Output = Result<Response<KeyValueInfo>, Error>> + Send + 'static,
+    {
+        let mut reads = self.reads.lock().unwrap();
+
+        if let Some(shared) = reads.get(&key).and_then(WeakShared::upgrade) {
+            return shared;
+        }
+
+        // Reads whose callers were all dropped before they finished never removed themselves.
+        reads.retain(|_, shared| shared.upgrade().is_some());
+
+        let in_flight = self.reads.clone();
+        let removed_key = key.clone();
+        let shared = async move {
+            let result = read.await.map_err(Arc::new);
+
+            // Later reads must make a new request, since this response may already be out of date.
+            in_flight.lock().unwrap().remove(&removed_key);
+
+            result
+        }
+        .boxed()
+        .shared();
+
+        if let Some(weak) = shared.downgrade() {
+            reads.insert(key, weak);
+        }
+
+        shared
+    }
+}
+
+impl Debug for InFlightReads {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("InFlightReads")
+            .field("reads", &self.reads.lock().unwrap().len())
+            .finish()
+    }
+}
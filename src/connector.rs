@@ -0,0 +1,153 @@
+//! A connector that hides the type of the connector inside it.
+//!
+//! Every API call is generic over the connector type of its `Client`, which would otherwise have
+//! to be named by any struct or trait object that holds a client. `DynClient`, a client whose
+//! connector type is `BoxConnector`, can be stored without a type parameter regardless of how the
+//! client connects to the cluster. Any client can be converted into one with `Client::into_dyn`,
+//! and a `BoxConnector` can also be passed to `ClientBuilder::build_with_connector` directly.
+//!
+//! The connector type of a client does not affect the API calls it makes, as described on
+//! `Client`, so converting a client only changes its type.
+
+use std::error::Error as StdError;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::Error as IoError;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::future::{BoxFuture, FutureExt, TryFutureExt};
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use hyper::Uri;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// The error type of a `BoxConnector`.
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// A connector that connects through another connector, whose type it hides.
+pub struct BoxConnector {
+    inner: Box<dyn CloneConnect>,
+}
+
+impl BoxConnector {
+    /// Constructs a new `BoxConnector` that connects through the given connector.
+    pub fn new<C>(connector: C) -> Self
+    where
+        C: Service<Uri> + Clone + Send + Sync + 'static,
+        C::Response: AsyncRead + AsyncWrite + Connection + Send + Unpin + 'static,
+        C::Error: Into<BoxError>,
+        C::Future: Send + 'static,
+    {
+        BoxConnector {
+            inner: Box::new(connector),
+        }
+    }
+}
+
+impl Clone for BoxConnector {
+    fn clone(&self) -> Self {
+        BoxConnector {
+            inner: self.inner.clone_box(),
+        }
+    }
+}
+
+impl Debug for BoxConnector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("BoxConnector").finish()
+    }
+}
+
+impl Service<Uri> for BoxConnector {
+    type Response = BoxConnection;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<BoxConnection, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        self.inner.call(uri)
+    }
+}
+
+/// A connection made by a `BoxConnector`.
+pub struct BoxConnection(Box<dyn Io>);
+
+impl Debug for BoxConnection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("BoxConnection").finish()
+    }
+}
+
+impl AsyncRead for BoxConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for BoxConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+impl Connection for BoxConnection {
+    fn connected(&self) -> Connected {
+        self.0.connected()
+    }
+}
+
+/// The connections a `BoxConnection` can hold.
+trait Io: AsyncRead + AsyncWrite + Connection + Send + Unpin {}
+
+impl<T> Io for T where T: AsyncRead + AsyncWrite + Connection + Send + Unpin {}
+
+/// An object-safe, cloneable version of the connectors a `BoxConnector` can be made from.
+trait CloneConnect: Send + Sync {
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>>;
+
+    fn call(&mut self, uri: Uri) -> BoxFuture<'static, Result<BoxConnection, BoxError>>;
+
+    fn clone_box(&self) -> Box<dyn CloneConnect>;
+}
+
+impl<C> CloneConnect for C
+where
+    C: Service<Uri> + Clone + Send + Sync + 'static,
+    C::Response: AsyncRead + AsyncWrite + Connection + Send + Unpin + 'static,
+    C::Error: Into<BoxError>,
+    C::Future: Send + 'static,
+{
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        Service::poll_ready(self, cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> BoxFuture<'static, Result<BoxConnection, BoxError>> {
+        Service::call(self, uri)
+            .map_ok(|connection| BoxConnection(Box::new(connection)))
+            .map_err(Into::into)
+            .boxed()
+    }
+
+    fn clone_box(&self) -> Box<dyn CloneConnect> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,248 @@
+//! Credentials that can change while a client is in use.
+//!
+//! A client built with `ClientBuilder::credentials_provider` asks its `CredentialsProvider` for
+//! the credentials for HTTP basic authentication on every request, and, if built with
+//! `ClientBuilder::build_https_with_config` or `ClientBuilder::build_rustls`, for its TLS
+//! configuration on every new connection. Connections that are already open keep the TLS
+//! configuration they were made with.
+//!
+//! `FileCredentials` is a provider that reads the credentials from files, and picks up changes to
+//! them when `FileCredentials::reload` is called, such as by the background task started by
+//! `spawn_reload`. This lets certificates and passwords be rotated without restarting the process.
+
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use log::error;
+
+use crate::client::BasicAuth;
+use crate::error::Error;
+use crate::task::{spawn_periodic, TaskHandle};
+#[cfg(any(feature = "tls", feature = "rustls"))]
+use crate::tls::TlsConfig;
+
+/// A source of the credentials a client authenticates with.
+pub trait CredentialsProvider: Debug + Send + Sync {
+    /// Returns the credentials for HTTP basic authentication, if any.
+    ///
+    /// Called for every request, so it should return quickly.
+    fn basic_auth(&self) -> Option<BasicAuth>;
+
+    /// Returns the TLS configuration, if the provider supplies one.
+    ///
+    /// Called for every new connection, so it should return quickly. The client only rebuilds its
+    /// TLS connector when a different `Arc` is returned than the previous time.
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    fn tls_config(&self) -> Option<Arc<TlsConfig>> {
+        None
+    }
+}
+
+impl CredentialsProvider for BasicAuth {
+    fn basic_auth(&self) -> Option<BasicAuth> {
+        Some(self.clone())
+    }
+}
+
+/// The files `FileCredentials` reads the credentials from.
+#[derive(Clone, Debug, Default)]
+pub struct CredentialFiles {
+    password_file: Option<PathBuf>,
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    tls_config: Option<TlsConfig>,
+    username: Option<String>,
+}
+
+impl CredentialFiles {
+    /// Constructs a new `CredentialFiles` without any credentials.
+    pub fn new() -> Self {
+        CredentialFiles::default()
+    }
+
+    /// Sets the username for HTTP basic authentication and the file containing the password.
+    ///
+    /// A trailing newline in the file is not considered part of the password.
+    pub fn basic_auth<P>(&mut self, username: &str, password_file: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.username = Some(username.to_owned());
+        self.password_file = Some(password_file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the TLS configuration, whose CA bundle, client certificate, and private key files are
+    /// read and watched for changes.
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    pub fn tls_config(&mut self, tls_config: TlsConfig) -> &mut Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// Reads the credentials from the files.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the files could not be read.
+    pub fn load(&self) -> Result<FileCredentials, Error> {
+        let modified = self.modified();
+        let loaded = self.read()?;
+
+        Ok(FileCredentials {
+            files: self.clone(),
+            state: RwLock::new(State { loaded, modified }),
+        })
+    }
+
+    /// Returns the paths of all the files.
+    fn paths(&self) -> Vec<&Path> {
+        #[allow(unused_mut)]
+        let mut paths: Vec<&Path> = self.password_file.iter().map(PathBuf::as_path).collect();
+
+        #[cfg(any(feature = "tls", feature = "rustls"))]
+        {
+            if let Some(ref tls_config) = self.tls_config {
+                paths.extend(tls_config.files());
+            }
+        }
+
+        paths
+    }
+
+    /// Returns the modification time of each file, if it can be determined.
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        self.paths()
+            .into_iter()
+            .map(|path| {
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Reads the credentials from the files.
+    fn read(&self) -> Result<Loaded, Error> {
+        let basic_auth = match (&self.username, &self.password_file) {
+            (Some(username), Some(password_file)) => {
+                let password = fs::read_to_string(password_file).map_err(|error| {
+                    Error::Credentials(
+                        format!("could not read {}: {}", password_file.display(), error).into(),
+                    )
+                })?;
+
+                Some(BasicAuth {
+                    username: username.clone(),
+                    password: password.trim_end_matches(&['\r', '\n'][..]).to_owned(),
+                })
+            }
+            _ => None,
+        };
+
+        Ok(Loaded {
+            basic_auth,
+            #[cfg(any(feature = "tls", feature = "rustls"))]
+            tls_config: match self.tls_config {
+                Some(ref tls_config) => Some(Arc::new(tls_config.load()?)),
+                None => None,
+            },
+        })
+    }
+}
+
+/// A `CredentialsProvider` that reads the credentials from files.
+#[derive(Debug)]
+pub struct FileCredentials {
+    files: CredentialFiles,
+    state: RwLock<State>,
+}
+
+/// The credentials read by `FileCredentials`, and when their files were last modified.
+#[derive(Debug)]
+struct State {
+    loaded: Loaded,
+    modified: Vec<Option<SystemTime>>,
+}
+
+/// The credentials read from the files.
+#[derive(Debug)]
+struct Loaded {
+    basic_auth: Option<BasicAuth>,
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    tls_config: Option<Arc<TlsConfig>>,
+}
+
+impl FileCredentials {
+    /// Reads the credentials from the files again if any of them changed since they were last
+    /// read.
+    ///
+    /// Returns whether the credentials were read again.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the files could not be read, in which case the previous credentials stay in
+    /// use.
+    pub fn reload(&self) -> Result<bool, Error> {
+        let modified = self.files.modified();
+
+        if modified == self.state.read().unwrap().modified {
+            return Ok(false);
+        }
+
+        let loaded = self.files.read()?;
+        *self.state.write().unwrap() = State { loaded, modified };
+
+        Ok(true)
+    }
+}
+
+impl CredentialsProvider for FileCredentials {
+    fn basic_auth(&self) -> Option<BasicAuth> {
+        self.state.read().unwrap().loaded.basic_auth.clone()
+    }
+
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    fn tls_config(&self) -> Option<Arc<TlsConfig>> {
+        self.state.read().unwrap().loaded.tls_config.clone()
+    }
+}
+
+/// A handle to the background task started by `spawn_reload`.
+///
+/// The task stops when the handle is dropped.
+#[derive(Debug)]
+pub struct ReloadHandle {
+    task_handle: TaskHandle,
+}
+
+impl ReloadHandle {
+    /// Stops the background task.
+    pub fn stop(self) {
+        drop(self.task_handle);
+    }
+}
+
+/// Starts a background task that calls `FileCredentials::reload` at the given interval.
+///
+/// Errors are logged, and the previous credentials stay in use until the files can be read
+/// again.
+///
+/// # Panics
+///
+/// Panics if called outside of a Tokio runtime.
+pub fn spawn_reload(credentials: &Arc<FileCredentials>, interval: Duration) -> ReloadHandle {
+    let credentials = credentials.clone();
+
+    let task_handle = spawn_periodic(interval, move || {
+        if let Err(error) = credentials.reload() {
+            error!("failed to reload etcd credentials: {}", error);
+        }
+
+        async {}
+    });
+
+    ReloadHandle { task_handle }
+}
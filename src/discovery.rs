@@ -0,0 +1,340 @@
+//! Discovery of cluster members through etcd's members API or DNS SRV records.
+//!
+//! A client only knows about the endpoints it was constructed with. When members are added to
+//! or removed from the cluster, or a member's address changes, these functions update the client's
+//! endpoints to the client URLs the cluster currently advertises. All clones of a client share
+//! its endpoints, so an update applies to every clone.
+//!
+//! Endpoints can also be looked up in the DNS SRV records etcd documents for client discovery:
+//! `_etcd-client-ssl._tcp.<domain>` for members serving HTTPS and `_etcd-client._tcp.<domain>` for
+//! members serving HTTP. Records are looked up through an `SrvResolver`. With the `srv` Cargo
+//! feature, `DnsResolver` uses the system's DNS configuration.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+#[cfg(feature = "srv")]
+use std::fmt::{Error as FmtError, Formatter};
+use std::time::Duration;
+
+use futures::future::{BoxFuture, FutureExt};
+use hyper::client::connect::Connect;
+use hyper::Uri;
+use log::error;
+#[cfg(feature = "srv")]
+use trust_dns_resolver::error::ResolveErrorKind;
+#[cfg(feature = "srv")]
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::client::{parse_endpoint, Client, Response};
+use crate::error::Error;
+use crate::first_ok::Result;
+use crate::members;
+use crate::task::{spawn_periodic, TaskHandle};
+
+/// A DNS SRV record pointing at a cluster member.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SrvRecord {
+    /// The host name of the cluster member.
+    pub target: String,
+    /// The port of the cluster member's client API.
+    pub port: u16,
+}
+
+/// A source of DNS SRV records.
+pub trait SrvResolver: Debug + Send + Sync {
+    /// Returns the SRV records for the given name, or an empty list if there are none.
+    fn resolve(&self, name: &str)
+        -> BoxFuture<'static, std::result::Result<Vec<SrvRecord>, Error>>;
+}
+
+/// Resolves SRV records from a fixed set of records, such as for tests.
+#[derive(Clone, Debug, Default)]
+pub struct StaticResolver {
+    records: HashMap<String, Vec<SrvRecord>>,
+}
+
+impl StaticResolver {
+    /// Constructs a new `StaticResolver` without any records.
+    pub fn new() -> Self {
+        StaticResolver::default()
+    }
+
+    /// Sets the records for the given name.
+    pub fn insert(&mut self, name: &str, records: Vec<SrvRecord>) -> &mut Self {
+        self.records.insert(name.to_string(), records);
+        self
+    }
+}
+
+impl SrvResolver for StaticResolver {
+    fn resolve(
+        &self,
+        name: &str,
+    ) -> BoxFuture<'static, std::result::Result<Vec<SrvRecord>, Error>> {
+        let records = self.records.get(name).cloned().unwrap_or_default();
+
+        async move { Ok(records) }.boxed()
+    }
+}
+
+/// Resolves SRV records using the system's DNS configuration.
+#[cfg(feature = "srv")]
+#[derive(Clone)]
+pub struct DnsResolver {
+    resolver: TokioAsyncResolver,
+}
+
+#[cfg(feature = "srv")]
+impl DnsResolver {
+    /// Constructs a new `DnsResolver` from the system's DNS configuration, such as
+    /// `/etc/resolv.conf` on Unix.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the system's DNS configuration could not be read.
+    pub async fn from_system_conf() -> std::result::Result<Self, Error> {
+        match TokioAsyncResolver::tokio_from_system_conf().await {
+            Ok(resolver) => Ok(DnsResolver { resolver }),
+            Err(error) => Err(Error::Resolve(Box::new(error))),
+        }
+    }
+}
+
+#[cfg(feature = "srv")]
+impl Debug for DnsResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::result::Result<(), FmtError> {
+        f.debug_struct("DnsResolver").finish()
+    }
+}
+
+#[cfg(feature = "srv")]
+impl SrvResolver for DnsResolver {
+    fn resolve(
+        &self,
+        name: &str,
+    ) -> BoxFuture<'static, std::result::Result<Vec<SrvRecord>, Error>> {
+        let resolver = self.resolver.clone();
+        let name = name.to_string();
+
+        async move {
+            match resolver.srv_lookup(name.as_str()).await {
+                Ok(lookup) => Ok(lookup
+                    .iter()
+                    .map(|srv| SrvRecord {
+                        target: srv.target().to_utf8(),
+                        port: srv.port(),
+                    })
+                    .collect()),
+                Err(error) => match *error.kind() {
+                    ResolveErrorKind::NoRecordsFound { .. } => Ok(vec![]),
+                    _ => Err(Error::Resolve(Box::new(error))),
+                },
+            }
+        }
+        .boxed()
+    }
+}
+
+/// A handle to the background task started by `spawn_sync` or `spawn_srv_sync`.
+///
+/// The task stops when the handle is dropped.
+#[derive(Debug)]
+pub struct SyncHandle {
+    task_handle: TaskHandle,
+}
+
+impl SyncHandle {
+    /// Stops the background task.
+    pub fn stop(self) {
+        drop(self.task_handle);
+    }
+}
+
+/// Replaces the client's endpoints with the client URLs of all current cluster members.
+///
+/// If the cluster advertises no valid client URLs, the client's endpoints are left unchanged.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call and whose endpoints to update.
+///
+/// # Errors
+///
+/// Fails if the members of the cluster could not be listed, in which case the client's endpoints
+/// are left unchanged.
+pub async fn sync<C>(client: &Client<C>) -> Result<Vec<String>>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    let response = members::list(client).await?;
+    let mut endpoints: Vec<Uri> = Vec::new();
+
+    for url in response.data.iter().flat_map(|member| &member.client_urls) {
+        match parse_endpoint(url) {
+            Ok(endpoint) => {
+                if !endpoints.contains(&endpoint) {
+                    endpoints.push(endpoint);
+                }
+            }
+            Err(error) => error!("ignoring invalid client URL {}: {}", url, error),
+        }
+    }
+
+    if !endpoints.is_empty() {
+        client.cluster().set_endpoints(endpoints);
+    }
+
+    Ok(Response {
+        data: client.endpoints(),
+        cluster_info: response.cluster_info,
+    })
+}
+
+/// Starts a background task that calls `sync` for the client at the given interval.
+///
+/// The first sync happens right away. Failed syncs are logged and leave the client's endpoints
+/// unchanged until the next one.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API calls and whose endpoints to update.
+/// * interval: How long to wait after each sync before starting the next one.
+///
+/// # Panics
+///
+/// Panics if called outside of a Tokio runtime.
+pub fn spawn_sync<C>(client: &Client<C>, interval: Duration) -> SyncHandle
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    let client = client.clone();
+
+    let task_handle = spawn_periodic(interval, move || {
+        let client = client.clone();
+
+        async move {
+            if let Err(error) = sync(&client).await {
+                error!("failed to sync cluster endpoints: {}", error);
+            }
+        }
+    });
+
+    SyncHandle { task_handle }
+}
+
+/// Looks up the endpoints of the cluster members in the DNS SRV records for the given domain.
+///
+/// Members found in `_etcd-client-ssl._tcp.<domain>` records are given HTTPS endpoints, which
+/// come first, and members found in `_etcd-client._tcp.<domain>` records are given HTTP
+/// endpoints.
+///
+/// # Parameters
+///
+/// * resolver: The resolver to look up the records with.
+/// * domain: The domain the cluster's records are published under.
+///
+/// # Errors
+///
+/// Fails if the records could not be resolved, or `Error::NoEndpoints` if there are none.
+pub async fn srv_endpoints(
+    resolver: &dyn SrvResolver,
+    domain: &str,
+) -> std::result::Result<Vec<String>, Error> {
+    let domain = domain.trim_end_matches('.');
+    let mut endpoints = Vec::new();
+
+    for &(service, scheme) in &[("_etcd-client-ssl", "https"), ("_etcd-client", "http")] {
+        let records = resolver
+            .resolve(&format!("{}._tcp.{}", service, domain))
+            .await?;
+
+        for record in records {
+            let target = record.target.trim_end_matches('.');
+            let endpoint = format!("{}://{}:{}", scheme, target, record.port);
+
+            if !endpoints.contains(&endpoint) {
+                endpoints.push(endpoint);
+            }
+        }
+    }
+
+    if endpoints.is_empty() {
+        return Err(Error::NoEndpoints);
+    }
+
+    Ok(endpoints)
+}
+
+/// Replaces the client's endpoints with those found in the DNS SRV records for the given domain.
+///
+/// # Parameters
+///
+/// * client: The `Client` whose endpoints to update.
+/// * resolver: The resolver to look up the records with.
+/// * domain: The domain the cluster's records are published under.
+///
+/// # Errors
+///
+/// Fails if the records could not be resolved, are empty, or contain an invalid endpoint, in
+/// which case the client's endpoints are left unchanged.
+pub async fn sync_srv<C>(
+    client: &Client<C>,
+    resolver: &dyn SrvResolver,
+    domain: &str,
+) -> std::result::Result<Vec<String>, Error>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    let mut endpoints = Vec::new();
+
+    for endpoint in srv_endpoints(resolver, domain).await? {
+        endpoints.push(parse_endpoint(&endpoint)?);
+    }
+
+    client.cluster().set_endpoints(endpoints);
+
+    Ok(client.endpoints())
+}
+
+/// Starts a background task that calls `sync_srv` for the client at the given interval.
+///
+/// The first sync happens right away. Failed syncs are logged and leave the client's endpoints
+/// unchanged until the next one.
+///
+/// # Parameters
+///
+/// * client: The `Client` whose endpoints to update.
+/// * resolver: The resolver to look up the records with.
+/// * domain: The domain the cluster's records are published under.
+/// * interval: How long to wait after each sync before starting the next one.
+///
+/// # Panics
+///
+/// Panics if called outside of a Tokio runtime.
+pub fn spawn_srv_sync<C, R>(
+    client: &Client<C>,
+    resolver: R,
+    domain: &str,
+    interval: Duration,
+) -> SyncHandle
+where
+    C: Clone + Connect + Send + Sync + 'static,
+    R: SrvResolver + Clone + 'static,
+{
+    let client = client.clone();
+    let domain = domain.to_string();
+
+    let task_handle = spawn_periodic(interval, move || {
+        let client = client.clone();
+        let resolver = resolver.clone();
+        let domain = domain.clone();
+
+        async move {
+            if let Err(error) = sync_srv(&client, &resolver, &domain).await {
+                error!("failed to sync cluster endpoints: {}", error);
+            }
+        }
+    });
+
+    SyncHandle { task_handle }
+}
@@ -0,0 +1,232 @@
+//! Per-endpoint settings for clients of clusters whose members are not all reached the same way.
+//!
+//! An `Endpoint` is the URL of a cluster member together with settings that only apply to that
+//! member: the credentials to authenticate with, a weight for `selector::Weighted`, a path prefix
+//! for members behind a reverse proxy, and, with the `tls` or `rustls` Cargo feature, the TLS
+//! configuration to connect with. Clients are built from endpoints with
+//! `ClientBuilder::from_endpoints`.
+//!
+//! Credentials, weights, and path prefixes work with every transport. Per-endpoint TLS
+//! configurations need a client built with `ClientBuilder::build_per_endpoint`, which connects to
+//! members over plain HTTP or HTTPS as their URLs say, so a single client can use HTTPS for some
+//! members and plain HTTP for others, such as a local proxy.
+//!
+//! Endpoints added to a client later, such as with `Client::add_endpoint` or the `discovery`
+//! module, use the client-wide settings.
+
+use std::sync::Arc;
+
+use hyper::Uri;
+
+#[cfg(any(feature = "tls", feature = "rustls"))]
+pub use self::connector::EndpointConnector;
+use crate::client::{parse_endpoint, BasicAuth};
+use crate::credentials::CredentialsProvider;
+use crate::error::Error;
+#[cfg(any(feature = "tls", feature = "rustls"))]
+use crate::tls::TlsConfig;
+
+/// The URL of a cluster member and the settings that only apply to it.
+#[derive(Clone, Debug)]
+pub struct Endpoint {
+    pub(crate) credentials: Option<Arc<dyn CredentialsProvider>>,
+    path_prefix: Option<String>,
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    pub(crate) tls_config: Option<TlsConfig>,
+    url: String,
+    pub(crate) weight: Option<u32>,
+}
+
+impl Endpoint {
+    /// Constructs a new `Endpoint` for the cluster member at the given URL, which uses the
+    /// client-wide settings.
+    pub fn new(url: &str) -> Self {
+        Endpoint {
+            credentials: None,
+            path_prefix: None,
+            #[cfg(any(feature = "tls", feature = "rustls"))]
+            tls_config: None,
+            url: url.to_owned(),
+            weight: None,
+        }
+    }
+
+    /// Sets the credentials for HTTP basic authentication to this member, instead of the
+    /// client-wide ones.
+    pub fn basic_auth(&mut self, basic_auth: BasicAuth) -> &mut Self {
+        self.credentials = Some(Arc::new(basic_auth));
+        self
+    }
+
+    /// Sets the provider to ask for the credentials for this member, instead of the client-wide
+    /// one.
+    pub fn credentials_provider<P>(&mut self, credentials: Arc<P>) -> &mut Self
+    where
+        P: CredentialsProvider + 'static,
+    {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Sets the path the member's API is served under, for members behind a reverse proxy.
+    ///
+    /// The prefix replaces any path in the URL. For example, with a prefix of `/etcd`, the keys
+    /// API of `https://proxy.example.com` is at `https://proxy.example.com/etcd/v2/keys`.
+    pub fn path_prefix(&mut self, path_prefix: &str) -> &mut Self {
+        self.path_prefix = Some(path_prefix.to_owned());
+        self
+    }
+
+    /// Sets the TLS configuration to connect to this member with, instead of the default one.
+    ///
+    /// Only clients built with `ClientBuilder::build_per_endpoint` use per-endpoint TLS
+    /// configurations. Since connections are made per host and port, endpoints that only differ
+    /// in their path share the TLS configuration of the first of them.
+    ///
+    /// A TLS configuration supplied by the client-wide credentials provider does not replace this
+    /// one, but one supplied by this member's own provider does.
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    pub fn tls_config(&mut self, tls_config: TlsConfig) -> &mut Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// Sets the weight of this member for `selector::Weighted`.
+    ///
+    /// Clients built from endpoints with weights use `selector::Weighted` unless another selector
+    /// is chosen with `ClientBuilder::endpoint_selector`, in which case the weights are ignored.
+    pub fn weight(&mut self, weight: u32) -> &mut Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    /// Parses the URL of the member, with the path prefix applied.
+    pub(crate) fn uri(&self) -> Result<Uri, Error> {
+        let uri = parse_endpoint(&self.url)?;
+
+        let path_prefix = match self.path_prefix {
+            Some(ref path_prefix) => path_prefix.trim_matches('/'),
+            None => return Ok(uri),
+        };
+
+        let scheme = uri.scheme_str().unwrap_or("http");
+        let authority = uri
+            .authority()
+            .map(|authority| authority.as_str())
+            .unwrap_or("");
+
+        let uri = if path_prefix.is_empty() {
+            format!("{}://{}/", scheme, authority)
+        } else {
+            format!("{}://{}/{}/", scheme, authority, path_prefix)
+        };
+
+        Ok(uri.parse()?)
+    }
+}
+
+#[cfg(any(feature = "tls", feature = "rustls"))]
+mod connector {
+    use std::collections::HashMap;
+    use std::error::Error as StdError;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    use futures::future::{poll_fn, FutureExt};
+    use hyper::client::connect::HttpConnector;
+    use hyper::service::Service;
+    use hyper::Uri;
+
+    use super::Endpoint;
+    use crate::credentials::CredentialsProvider;
+    use crate::error::Error;
+    use crate::tls::TlsConfig;
+
+    #[cfg(all(feature = "rustls", not(feature = "tls")))]
+    use crate::rustls::HttpsConnector as Transport;
+    #[cfg(feature = "tls")]
+    use crate::tls::HttpsConnector as Transport;
+
+    /// A connector that connects to each cluster member with the TLS configuration of its
+    /// `Endpoint`, or over plain HTTP if its URL says so.
+    ///
+    /// It uses the platform's native TLS library with the `tls` Cargo feature, and rustls
+    /// otherwise.
+    #[derive(Clone, Debug)]
+    pub struct EndpointConnector {
+        by_authority: Arc<HashMap<String, Transport>>,
+        default: Transport,
+    }
+
+    impl EndpointConnector {
+        /// Constructs a new `EndpointConnector` for the given endpoints.
+        ///
+        /// Endpoints without their own TLS configuration, or added later, use the default one,
+        /// which the client-wide credentials provider can replace. The TLS configuration of an
+        /// endpoint can only be replaced by its own credentials provider.
+        pub(crate) fn new(
+            http: HttpConnector,
+            endpoints: &[Endpoint],
+            credentials: Option<Arc<dyn CredentialsProvider>>,
+        ) -> Result<Self, Error> {
+            let mut by_authority = HashMap::new();
+
+            for endpoint in endpoints {
+                let tls_config = match endpoint.tls_config {
+                    Some(ref tls_config) => tls_config,
+                    None => continue,
+                };
+
+                let authority = match endpoint.uri()?.authority() {
+                    Some(authority) => authority.to_string(),
+                    None => continue,
+                };
+
+                if by_authority.contains_key(&authority) {
+                    continue;
+                }
+
+                let transport =
+                    Transport::new(http.clone(), tls_config, endpoint.credentials.clone())?;
+
+                by_authority.insert(authority, transport);
+            }
+
+            Ok(EndpointConnector {
+                by_authority: Arc::new(by_authority),
+                default: Transport::new(http, &TlsConfig::new(), credentials)?,
+            })
+        }
+    }
+
+    impl Service<Uri> for EndpointConnector {
+        type Response = <Transport as Service<Uri>>::Response;
+        type Error = Box<dyn StdError + Send + Sync>;
+        type Future = <Transport as Service<Uri>>::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.default.poll_ready(cx)
+        }
+
+        fn call(&mut self, uri: Uri) -> Self::Future {
+            let transport = uri
+                .authority()
+                .and_then(|authority| self.by_authority.get(authority.as_str()));
+
+            match transport {
+                // The transports of endpoints are shared between clones of the connector, so each
+                // connection waits for its own clone to be ready instead of `poll_ready`.
+                Some(transport) => {
+                    let mut transport = transport.clone();
+
+                    async move {
+                        poll_fn(|cx| transport.poll_ready(cx)).await?;
+                        transport.call(uri).await
+                    }
+                    .boxed()
+                }
+                None => self.default.call(uri),
+            }
+        }
+    }
+}
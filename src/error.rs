@@ -3,15 +3,20 @@
 use std::convert::From;
 use std::error::Error as StdError;
 use std::fmt::{Display, Error as FmtError, Formatter};
+use std::sync::Arc;
 
 use http::uri::InvalidUri;
 use hyper::{Error as HttpError, StatusCode};
 #[cfg(feature = "tls")]
-use native_tls::Error as TlsError;
+use native_tls::Error as NativeTlsError;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Error as SerializationError;
+#[cfg(feature = "rustls")]
+use tokio_rustls::rustls::TLSError as RustlsError;
 use url::ParseError as UrlError;
 
+use crate::client::ClusterInfo;
+
 /// An error returned by an etcd API endpoint.
 ///
 /// This is a logical error, as opposed to other types of errors that may occur when using this
@@ -20,6 +25,10 @@ use url::ParseError as UrlError;
 pub struct ApiError {
     /// The key that was being operated upon or reason for the failure.
     pub cause: Option<String>,
+    /// The ID of the cluster of the member that answered, from the `X-Etcd-Cluster-Id` HTTP
+    /// header.
+    #[serde(skip)]
+    pub cluster_id: Option<String>,
     /// The etcd error code.
     #[serde(rename = "errorCode")]
     pub error_code: u64,
@@ -46,22 +55,74 @@ impl StdError for ApiError {
 pub enum Error {
     /// An error returned by an etcd API endpoint.
     Api(ApiError),
+    /// An error returned when no cluster member could answer a request, either because it could
+    /// not be reached or because it failed with a server error. Contains the error from each
+    /// member that was tried.
+    ClusterUnavailable(Vec<Error>),
+    /// An error returned when a cluster member answered with a different cluster ID than the one
+    /// the client expects, which suggests that the client's endpoints belong to more than one
+    /// cluster.
+    ClusterIdMismatch {
+        /// The cluster ID the client expects.
+        expected: String,
+        /// The cluster ID the member answered with.
+        actual: String,
+    },
+    /// An error returned by a read that was coalesced with an identical read in flight, when the
+    /// error that read failed with cannot be copied for each of its callers. Contains that error.
+    /// See `ClientBuilder::coalesce_reads`.
+    Coalesced(Arc<Error>),
+    /// An error returned when the credentials of a `credentials::FileCredentials` could not be
+    /// read.
+    Credentials(Box<dyn StdError + Send + Sync>),
     /// An error at the HTTP protocol layer.
     Http(HttpError),
     /// An error returned when invalid conditions have been provided for a compare-and-delete or
     /// compare-and-swap operation.
     InvalidConditions,
+    /// An error returned when a key cannot be used with etcd's key-value API. See `kv::KeyPath`.
+    InvalidKey(String),
+    /// An error returned when a request that is not idempotent failed in a way that leaves it
+    /// unknown whether etcd applied it. Such a request is not made again to another cluster
+    /// member, since it could be applied twice. Contains the error of the failed attempt.
+    Indeterminate(Box<Error>),
     /// An error returned when an etcd cluster member's endpoint is not a valid URI.
     InvalidUri(InvalidUri),
     /// An error returned when the URL for a specific API endpoint cannot be generated.
     InvalidUrl(UrlError),
+    /// An error returned by a layer added with `ClientBuilder::layer`. See the `middleware` module.
+    Middleware(Box<dyn StdError + Send + Sync>),
     /// An error returned when attempting to create a client without at least one member endpoint.
     NoEndpoints,
+    /// An error returned when the DNS SRV records used to discover cluster members could not be
+    /// resolved.
+    Resolve(Box<dyn StdError + Send + Sync>),
+    /// An error returned when the body of a response is larger than the client's maximum response
+    /// size. See `ClientBuilder::max_response_size`.
+    ResponseTooLarge {
+        /// The maximum response size in bytes.
+        limit: usize,
+    },
+    /// An error returned when the runtime of a `blocking::Client` could not be started.
+    #[cfg(feature = "blocking")]
+    Runtime(std::io::Error),
     /// An error returned when attempting to deserializing invalid JSON.
     Serialization(SerializationError),
-    /// An error returned when configuring TLS.
-    #[cfg(feature = "tls")]
-    Tls(TlsError),
+    /// An error returned by a client with monotonic reads when a cluster member answered a read
+    /// with data older than what the client has already observed, because the member is lagging
+    /// behind the rest of the cluster.
+    StaleRead {
+        /// The etcd index of the response.
+        index: u64,
+        /// The highest etcd index the client has observed.
+        min_index: u64,
+    },
+    /// An error returned when a request to an etcd cluster member did not receive a response
+    /// within the client's request timeout.
+    Timeout,
+    /// An error returned when configuring TLS, by either the native TLS library or rustls.
+    #[cfg(any(feature = "tls", feature = "rustls"))]
+    Tls(Box<dyn StdError + Send + Sync>),
     /// An error returned when an unexpected HTTP status code is returned by the server.
     UnexpectedStatus(StatusCode),
 }
@@ -70,14 +131,57 @@ impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
         match *self {
             Error::Api(ref error) => write!(f, "{}", error),
+            Error::ClusterUnavailable(ref errors) => {
+                write!(f, "no etcd cluster member could answer the request")?;
+
+                for error in errors {
+                    write!(f, "; {}", error)?;
+                }
+
+                Ok(())
+            }
+            Error::ClusterIdMismatch {
+                ref expected,
+                ref actual,
+            } => write!(
+                f,
+                "expected a response from etcd cluster {}, got one from cluster {}",
+                expected, actual
+            ),
+            Error::Coalesced(ref error) => write!(f, "{}", error),
+            Error::Credentials(ref error) => write!(f, "{}", error),
             Error::Http(ref error) => write!(f, "{}", error),
-            ref error @ Error::InvalidConditions => write!(f, "{}", error.to_string()),
+            Error::Indeterminate(ref error) => write!(
+                f,
+                "it is unknown whether the etcd server applied the request: {}",
+                error
+            ),
+            Error::InvalidConditions => write!(f, "current value or modified index is required"),
+            Error::InvalidKey(ref reason) => write!(f, "{}", reason),
             Error::InvalidUri(ref error) => write!(f, "{}", error),
             Error::InvalidUrl(ref error) => write!(f, "{}", error),
-            ref error @ Error::NoEndpoints => write!(f, "{}", error.to_string()),
-            #[cfg(feature = "tls")]
+            Error::Middleware(ref error) => write!(f, "{}", error),
+            Error::NoEndpoints => write!(f, "at least one endpoint is required to create a Client"),
+            Error::Resolve(ref error) => write!(f, "{}", error),
+            Error::ResponseTooLarge { ref limit } => write!(
+                f,
+                "the response from the etcd server is larger than the maximum of {} bytes",
+                limit
+            ),
+            #[cfg(feature = "blocking")]
+            Error::Runtime(ref error) => write!(f, "{}", error),
+            Error::Timeout => write!(f, "the request to the etcd server timed out"),
+            #[cfg(any(feature = "tls", feature = "rustls"))]
             Error::Tls(ref error) => write!(f, "{}", error),
             Error::Serialization(ref error) => write!(f, "{}", error),
+            Error::StaleRead {
+                ref index,
+                ref min_index,
+            } => write!(
+                f,
+                "the etcd server answered at index {}, but index {} has already been observed",
+                index, min_index
+            ),
             Error::UnexpectedStatus(ref status) => write!(
                 f,
                 "the etcd server returned an unexpected HTTP status code: {}",
@@ -91,19 +195,112 @@ impl StdError for Error {
     fn description(&self) -> &str {
         match *self {
             Error::Api(_) => "the etcd server returned an error",
+            Error::ClusterUnavailable(_) => "no etcd cluster member could answer the request",
+            Error::ClusterIdMismatch { .. } => "a response came from an unexpected etcd cluster",
+            Error::Coalesced(_) => "a read coalesced with identical reads failed",
+            Error::Credentials(_) => "the credentials could not be read",
             Error::Http(_) => "an error occurred during the HTTP request",
+            Error::Indeterminate(_) => "it is unknown whether the etcd server applied the request",
             Error::InvalidConditions => "current value or modified index is required",
+            Error::InvalidKey(_) => "a key cannot be used with etcd's key-value API",
             Error::InvalidUri(_) => "a supplied endpoint could not be parsed as a URI",
             Error::InvalidUrl(_) => "a URL for the request could not be generated",
+            Error::Middleware(_) => "a middleware layer failed the request",
             Error::NoEndpoints => "at least one endpoint is required to create a Client",
-            #[cfg(feature = "tls")]
+            Error::Resolve(_) => "DNS SRV records for the cluster could not be resolved",
+            Error::ResponseTooLarge { .. } => "the response from the etcd server is too large",
+            #[cfg(feature = "blocking")]
+            Error::Runtime(_) => "the runtime of a blocking client could not be started",
+            Error::Timeout => "the request to the etcd server timed out",
+            #[cfg(any(feature = "tls", feature = "rustls"))]
             Error::Tls(_) => "an error occurred configuring TLS",
             Error::Serialization(_) => "an error occurred deserializing JSON",
+            Error::StaleRead { .. } => "the etcd server answered with stale data",
             Error::UnexpectedStatus(_) => "the etcd server returned an unexpected HTTP status code",
         }
     }
 }
 
+impl Error {
+    /// Constructs the error for an unsuccessful response from an etcd API endpoint.
+    ///
+    /// The body is expected to describe an `ApiError`, except for server errors, which may come
+    /// from a proxy in front of etcd rather than etcd itself.
+    pub(crate) fn from_response(
+        status: StatusCode,
+        cluster_info: ClusterInfo,
+        body: &[u8],
+    ) -> Error {
+        match serde_json::from_slice::<ApiError>(body) {
+            Ok(error) => Error::Api(ApiError {
+                cluster_id: cluster_info.cluster_id,
+                ..error
+            }),
+            Err(_) if status.is_server_error() => Error::UnexpectedStatus(status),
+            Err(error) => Error::Serialization(error),
+        }
+    }
+
+    /// Returns a copy of the error, if it can be copied.
+    ///
+    /// Errors that wrap an error of another crate cannot, except inside `Error::Coalesced`.
+    pub(crate) fn try_clone(&self) -> Option<Error> {
+        let error = match *self {
+            Error::Api(ref error) => Error::Api(error.clone()),
+            Error::ClusterUnavailable(ref errors) => Error::ClusterUnavailable(
+                errors
+                    .iter()
+                    .map(Error::try_clone)
+                    .collect::<Option<Vec<Error>>>()?,
+            ),
+            Error::ClusterIdMismatch {
+                ref expected,
+                ref actual,
+            } => Error::ClusterIdMismatch {
+                expected: expected.clone(),
+                actual: actual.clone(),
+            },
+            Error::Coalesced(ref error) => Error::Coalesced(error.clone()),
+            Error::Indeterminate(ref error) => Error::Indeterminate(Box::new(error.try_clone()?)),
+            Error::InvalidConditions => Error::InvalidConditions,
+            Error::InvalidKey(ref reason) => Error::InvalidKey(reason.clone()),
+            Error::InvalidUrl(error) => Error::InvalidUrl(error),
+            Error::NoEndpoints => Error::NoEndpoints,
+            Error::ResponseTooLarge { limit } => Error::ResponseTooLarge { limit },
+            Error::StaleRead { index, min_index } => Error::StaleRead { index, min_index },
+            Error::Timeout => Error::Timeout,
+            Error::UnexpectedStatus(status) => Error::UnexpectedStatus(status),
+            _ => return None,
+        };
+
+        Some(error)
+    }
+
+    /// Returns whether the request that failed with this error certainly never reached the cluster
+    /// member, so that it can be made again even if it is not idempotent.
+    pub(crate) fn is_connect(&self) -> bool {
+        match *self {
+            Error::Http(ref error) => error.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the request that failed with this error should be made to another cluster
+    /// member.
+    ///
+    /// This is the case for errors where the member could not be reached or failed internally,
+    /// but not for logical errors, which every other member would answer the same way.
+    pub(crate) fn should_fail_over(&self) -> bool {
+        match *self {
+            Error::Http(_) | Error::StaleRead { .. } | Error::Timeout => true,
+            // etcd answers Raft errors, such as an ongoing leader election, with a 500.
+            Error::Api(ref error) => error.error_code >= 300 && error.error_code < 400,
+            Error::UnexpectedStatus(status) => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
 impl From<HttpError> for Error {
     fn from(error: HttpError) -> Error {
         Error::Http(error)
@@ -111,9 +308,16 @@ impl From<HttpError> for Error {
 }
 
 #[cfg(feature = "tls")]
-impl From<TlsError> for Error {
-    fn from(error: TlsError) -> Error {
-        Error::Tls(error)
+impl From<NativeTlsError> for Error {
+    fn from(error: NativeTlsError) -> Error {
+        Error::Tls(Box::new(error))
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl From<RustlsError> for Error {
+    fn from(error: RustlsError) -> Error {
+        Error::Tls(Box::new(error))
     }
 }
 
@@ -138,8 +342,8 @@ impl From<InvalidUri> for Error {
 /// An error returned by `kv::watch`.
 #[derive(Debug)]
 pub enum WatchError {
-    /// An error for each failed request to an etcd member.
-    Other(Vec<Error>),
+    /// An error returned by the request to the etcd cluster.
+    Other(Error),
     /// The supplied timeout was reached before any request successfully completed.
     Timeout,
 }
@@ -147,8 +351,8 @@ pub enum WatchError {
 impl Display for WatchError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
         match *self {
-            WatchError::Timeout => write!(f, "{}", self.to_string()),
-            ref other => other.fmt(f),
+            WatchError::Timeout => write!(f, "operation timed out"),
+            WatchError::Other(ref error) => write!(f, "{}", error),
         }
     }
 }
@@ -1,29 +1,136 @@
+use crate::cluster::Cluster;
 use crate::{Error, Response};
 use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
 
+use futures::future::BoxFuture;
 use hyper::Uri;
 
-/// Executes the given closure with each cluster member and short-circuit returns the first
-/// successful result. If all members are exhausted without success, the final error is
-/// returned.
-pub async fn first_ok<F, U, V, E>(
-    endpoints: Vec<Uri>,
+/// Whether a request can safely be made again after an attempt failed in a way that leaves it
+/// unknown whether etcd applied it.
+pub enum Idempotency<T> {
+    /// Making the request again has no effect beyond that of the first attempt.
+    Idempotent,
+    /// Making the request again could apply it twice, so it is not made again.
+    NonIdempotent,
+    /// Making the request again could apply it twice, but the given check can tell whether the
+    /// failed attempt was applied. The check returns the response for the applied request, or
+    /// nothing if the request was not applied and can be made again.
+    Verifiable(Verifier<T>),
+}
+
+impl<T> Idempotency<T> {
+    /// Returns whether making the request again has no effect beyond that of the first attempt.
+    pub fn is_idempotent(&self) -> bool {
+        match *self {
+            Idempotency::Idempotent => true,
+            Idempotency::NonIdempotent | Idempotency::Verifiable(_) => false,
+        }
+    }
+}
+
+/// A check whether a failed attempt of a non-idempotent request was applied.
+pub type Verifier<T> = Box<
+    dyn Fn() -> BoxFuture<'static, std::result::Result<Option<Response<T>>, Error>> + Send + Sync,
+>;
+
+/// Executes the given closure with each cluster member, in the order chosen by the endpoint
+/// selector, and short-circuit returns the first answer from a member: either a successful result
+/// or a logical error such as a missing key. Members whose circuit breaker is open are skipped,
+/// unless all of them are.
+///
+/// The request is only made to the next member when a member could not be reached or failed with
+/// a server error, after the transport's retries against that member are exhausted. If all members
+/// are exhausted this way, `Error::ClusterUnavailable` is returned with the error from each member.
+///
+/// A successful response or logical error from a member of a different cluster than the client
+/// expects fails with `Error::ClusterIdMismatch` instead.
+///
+/// Requests that are not idempotent are only made again when the failed attempt certainly did not
+/// reach the member, or when the given `Idempotency` can verify that it was not applied.
+/// Otherwise, `Error::Indeterminate` is returned.
+pub async fn first_ok<F, U, T>(
+    cluster: Arc<Cluster>,
+    idempotency: Idempotency<T>,
     callback: F,
-) -> std::result::Result<V, Vec<E>>
+) -> Result<T>
 where
     F: Fn(Uri) -> U,
-    U: Future<Output = std::result::Result<V, E>>,
+    U: Future<Output = Result<T>>,
 {
+    let endpoints = cluster.endpoints_to_try();
     let mut errors = Vec::with_capacity(endpoints.len());
 
     for endpoint in endpoints {
-        match (callback)(endpoint).await {
-            Ok(result) => return Ok(result),
-            Err(err) => errors.push(err),
+        // Another API call may have made the trial call of a half-open breaker in the meantime.
+        if !cluster.allow(&endpoint) {
+            continue;
+        }
+
+        let start = Instant::now();
+
+        let error = match (callback)(endpoint.clone()).await {
+            // The member answered, it is only behind the rest of the cluster, so its health is
+            // left as it was.
+            Err(error @ Error::StaleRead { .. }) => {
+                errors.push(error);
+                continue;
+            }
+            Err(error) if error.should_fail_over() => error,
+            Ok(response) => {
+                if let Err(error) =
+                    cluster.check_cluster_id(response.cluster_info.cluster_id.as_deref())
+                {
+                    cluster.failed(&endpoint);
+                    return Err(error);
+                }
+
+                if let Some(index) = response.cluster_info.etcd_index {
+                    cluster.observe_etcd_index(index);
+                }
+
+                cluster.succeeded(&endpoint, start.elapsed());
+                return Ok(response);
+            }
+            Err(error) => {
+                if let Error::Api(ref error) = error {
+                    if let Err(error) = cluster.check_cluster_id(error.cluster_id.as_deref()) {
+                        cluster.failed(&endpoint);
+                        return Err(error);
+                    }
+
+                    cluster.observe_etcd_index(error.index);
+                }
+
+                cluster.succeeded(&endpoint, start.elapsed());
+                return Err(error);
+            }
+        };
+
+        if !error.is_connect() {
+            match idempotency {
+                Idempotency::Idempotent => {}
+                Idempotency::NonIdempotent => {
+                    cluster.failed(&endpoint);
+                    return Err(Error::Indeterminate(Box::new(error)));
+                }
+                Idempotency::Verifiable(ref verify) => match verify().await {
+                    Ok(Some(response)) => return Ok(response),
+                    Ok(None) => {}
+                    Err(_) => {
+                        cluster.failed(&endpoint);
+                        return Err(Error::Indeterminate(Box::new(error)));
+                    }
+                },
+            }
         }
+
+        cluster.failed(&endpoint);
+        errors.push(error);
     }
 
-    Err(errors)
+    Err(Error::ClusterUnavailable(errors))
 }
 
-pub type Result<T> = std::result::Result<Response<T>, Vec<Error>>;
+pub type Result<T> = std::result::Result<Response<T>, Error>;
@@ -0,0 +1,218 @@
+//! Health tracking of the cluster members a client makes API calls to.
+//!
+//! A client configured with a `CircuitBreaker` keeps a breaker for each endpoint. Once an endpoint
+//! has failed `failure_threshold` API calls in a row, its breaker opens and the endpoint is
+//! skipped by later API calls, so they don't wait for it to time out again. After `reset_timeout`,
+//! the breaker lets a single API call through to try the endpoint again, and closes if it
+//! succeeds.
+//!
+//! Endpoints are only skipped while there is at least one endpoint whose breaker lets API calls
+//! through. If all breakers are open, API calls try every endpoint as usual.
+//!
+//! Besides API calls, `probe` and `spawn_probe` check the endpoints' health check API endpoint, so
+//! that members that went down are noticed and members that recovered are used again without
+//! waiting for an API call.
+
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+use hyper::client::connect::Connect;
+use hyper::Uri;
+
+use crate::client::{build_url, display_endpoint, Client, Health};
+use crate::task::{spawn_periodic, TaskHandle};
+
+/// Configuration of the per-endpoint circuit breakers.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CircuitBreaker {
+    /// How many API calls in a row an endpoint has to fail for its breaker to open.
+    pub failure_threshold: u32,
+    /// How long an open breaker skips its endpoint before letting an API call through again.
+    pub reset_timeout: Duration,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        CircuitBreaker {
+            failure_threshold: 5,
+            reset_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The state of an endpoint's circuit breaker.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EndpointState {
+    /// The endpoint is considered healthy and receives API calls.
+    Closed,
+    /// The endpoint failed too often and is skipped by API calls.
+    Open,
+    /// The endpoint was skipped for the breaker's reset timeout, and the next API call to it
+    /// decides whether the breaker closes or opens again.
+    HalfOpen,
+}
+
+/// The circuit breaker of a single endpoint.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Breaker {
+    /// Failed API calls in a row.
+    failures: u32,
+    /// When the breaker last opened, or last let a trial API call through if half-open.
+    since: Instant,
+    state: EndpointState,
+}
+
+impl Breaker {
+    /// Constructs a new, closed `Breaker`.
+    pub fn new() -> Self {
+        Breaker {
+            failures: 0,
+            since: Instant::now(),
+            state: EndpointState::Closed,
+        }
+    }
+
+    /// Returns the state of the breaker.
+    pub fn state(&self, config: &CircuitBreaker) -> EndpointState {
+        match self.state {
+            EndpointState::Open if self.since.elapsed() >= config.reset_timeout => {
+                EndpointState::HalfOpen
+            }
+            state => state,
+        }
+    }
+
+    /// Returns whether the endpoint is skipped by API calls, because the breaker is open or the
+    /// trial call of the half-open breaker is already being made.
+    pub fn is_open(&self, config: &CircuitBreaker) -> bool {
+        self.state != EndpointState::Closed && self.since.elapsed() < config.reset_timeout
+    }
+
+    /// Returns whether an API call may be made to the endpoint, and if it is the trial call of a
+    /// half-open breaker, keeps other calls from being made until the reset timeout passes again.
+    pub fn allow(&mut self, config: &CircuitBreaker) -> bool {
+        match self.state {
+            EndpointState::Closed => true,
+            _ if self.since.elapsed() >= config.reset_timeout => {
+                self.since = Instant::now();
+                self.state = EndpointState::HalfOpen;
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records that the endpoint answered.
+    pub fn succeeded(&mut self) {
+        self.failures = 0;
+        self.state = EndpointState::Closed;
+    }
+
+    /// Records that the endpoint could not answer.
+    pub fn failed(&mut self, config: &CircuitBreaker) {
+        self.failures = self.failures.saturating_add(1);
+
+        if self.state != EndpointState::Closed || self.failures >= config.failure_threshold {
+            self.since = Instant::now();
+            self.state = EndpointState::Open;
+        }
+    }
+}
+
+/// A handle to the background task started by `spawn_probe`.
+///
+/// The task stops when the handle is dropped.
+#[derive(Debug)]
+pub struct ProbeHandle {
+    task_handle: TaskHandle,
+}
+
+impl ProbeHandle {
+    /// Stops the background task.
+    pub fn stop(self) {
+        drop(self.task_handle);
+    }
+}
+
+/// Returns the circuit breaker state of each of the client's endpoints.
+///
+/// Endpoints of a client without a `CircuitBreaker` are always `EndpointState::Closed`.
+pub fn states<C>(client: &Client<C>) -> Vec<(String, EndpointState)>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    display_states(client.cluster().endpoint_states())
+}
+
+/// Checks the health of each of the client's endpoints, and updates their circuit breakers
+/// accordingly.
+///
+/// Unlike API calls, the check is made to every endpoint, regardless of its breaker's state.
+///
+/// Returns the circuit breaker state of each endpoint after the check.
+pub async fn probe<C>(client: &Client<C>) -> Vec<(String, EndpointState)>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    let cluster = client.cluster();
+
+    let checks = cluster
+        .endpoints()
+        .to_vec()
+        .into_iter()
+        .map(|endpoint| async move {
+            let healthy = match build_url(&endpoint, "health") {
+                Ok(uri) => match client.request::<Health>(uri).await {
+                    Ok(response) => response.data.health == "true",
+                    Err(_) => false,
+                },
+                Err(_) => false,
+            };
+
+            (endpoint, healthy)
+        });
+
+    for (endpoint, healthy) in join_all(checks).await {
+        cluster.record_health(&endpoint, healthy);
+    }
+
+    display_states(cluster.endpoint_states())
+}
+
+/// Converts the endpoints of breaker states into the form they are shown to users in.
+fn display_states(states: Vec<(Uri, EndpointState)>) -> Vec<(String, EndpointState)> {
+    states
+        .into_iter()
+        .map(|(endpoint, state)| (display_endpoint(&endpoint), state))
+        .collect()
+}
+
+/// Starts a background task that calls `probe` for the client at the given interval.
+///
+/// The first probe happens right away.
+///
+/// # Parameters
+///
+/// * client: The `Client` whose endpoints to check.
+/// * interval: How long to wait after each probe before starting the next one.
+///
+/// # Panics
+///
+/// Panics if called outside of a Tokio runtime.
+pub fn spawn_probe<C>(client: &Client<C>, interval: Duration) -> ProbeHandle
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    let client = client.clone();
+
+    let task_handle = spawn_periodic(interval, move || {
+        let client = client.clone();
+
+        async move {
+            probe(&client).await;
+        }
+    });
+
+    ProbeHandle { task_handle }
+}
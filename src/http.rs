@@ -1,80 +1,194 @@
-use base64::encode;
-use http::header::{AUTHORIZATION, CONTENT_TYPE};
-use http::request::Builder;
-use hyper::client::connect::Connect;
-use hyper::client::ResponseFuture;
-use hyper::{Body, Client as Hyper, Method, Request, Uri};
+use std::future::Future;
+use std::sync::Arc;
 
-use crate::client::BasicAuth;
+use http::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::body::{Bytes, HttpBody};
+use hyper::{Body, Method, Request, Response, Uri};
+use tokio::time::timeout_at;
+
+use crate::error::Error;
+use crate::first_ok::Idempotency;
+use crate::middleware::{send, Deadline, LongPoll, NonIdempotent, Transport};
 
 #[derive(Clone, Debug)]
-pub struct HttpClient<C>
-where
-    C: Clone + Connect + Sync + Send + 'static,
-{
-    basic_auth: Option<BasicAuth>,
-    hyper: Hyper<C>,
+pub struct HttpClient {
+    headers: Arc<HeaderMap>,
+    max_response_size: Option<usize>,
+    non_idempotent: bool,
+    transport: Transport,
 }
 
-impl<C> HttpClient<C>
-where
-    C: Clone + Connect + Send + Sync + 'static,
-{
-    /// Constructs a new `HttpClient`.
-    pub fn new(hyper: Hyper<C>, basic_auth: Option<BasicAuth>) -> Self {
-        HttpClient { basic_auth, hyper }
+impl HttpClient {
+    /// Constructs a new `HttpClient` that sends requests through the given transport, and reads
+    /// response bodies up to the given size.
+    pub fn new(transport: Transport, max_response_size: Option<usize>) -> Self {
+        HttpClient {
+            headers: Arc::new(HeaderMap::new()),
+            max_response_size,
+            non_idempotent: false,
+            transport,
+        }
+    }
+
+    /// Returns a copy of the client that sets the given header on every request.
+    pub fn with_header(&self, name: HeaderName, value: HeaderValue) -> Self {
+        let mut client = self.clone();
+        Arc::make_mut(&mut client.headers).insert(name, value);
+
+        client
+    }
+
+    /// Returns a copy of the client for requests with the given idempotency. Requests that are not
+    /// idempotent are only made again when the failed attempt certainly did not reach the member.
+    pub fn with_idempotency<T>(&self, idempotency: &Idempotency<T>) -> Self {
+        HttpClient {
+            headers: self.headers.clone(),
+            max_response_size: self.max_response_size,
+            non_idempotent: !idempotency.is_idempotent(),
+            transport: self.transport.clone(),
+        }
+    }
+
+    /// Returns the headers set on every request.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the maximum size in bytes of a response body the client reads, if any.
+    pub fn max_response_size(&self) -> Option<usize> {
+        self.max_response_size
     }
 
     /// Makes a DELETE request to etcd.
-    pub fn delete(&self, uri: Uri) -> ResponseFuture {
-        self.request(Method::DELETE, uri)
+    pub fn delete(&self, uri: Uri) -> impl Future<Output = Result<Response<Body>, Error>> {
+        self.request(Method::DELETE, uri, None, false)
     }
 
     /// Makes a GET request to etcd.
-    pub fn get(&self, uri: Uri) -> ResponseFuture {
-        self.request(Method::GET, uri)
+    pub fn get(&self, uri: Uri) -> impl Future<Output = Result<Response<Body>, Error>> {
+        self.request(Method::GET, uri, None, false)
+    }
+
+    /// Makes a GET request to etcd that may wait indefinitely for a response, such as a watch.
+    pub fn long_poll(&self, uri: Uri) -> impl Future<Output = Result<Response<Body>, Error>> {
+        self.request(Method::GET, uri, None, true)
     }
 
     /// Makes a POST request to etcd.
-    pub fn post(&self, uri: Uri, body: String) -> ResponseFuture {
-        self.request_with_body(Method::POST, uri, body)
+    pub fn post(
+        &self,
+        uri: Uri,
+        body: String,
+    ) -> impl Future<Output = Result<Response<Body>, Error>> {
+        self.request(Method::POST, uri, Some(body), false)
     }
 
     /// Makes a PUT request to etcd.
-    pub fn put(&self, uri: Uri, body: String) -> ResponseFuture {
-        self.request_with_body(Method::PUT, uri, body)
+    pub fn put(
+        &self,
+        uri: Uri,
+        body: String,
+    ) -> impl Future<Output = Result<Response<Body>, Error>> {
+        self.request(Method::PUT, uri, Some(body), false)
+    }
+
+    /// Reads the body of a response from etcd.
+    ///
+    /// Fails with `Error::ResponseTooLarge` as soon as the body is known to be larger than the
+    /// maximum response size, and with `Error::Timeout` if the body isn't read by the deadline of
+    /// the request.
+    pub async fn read_body(&self, response: Response<Body>) -> Result<Bytes, Error> {
+        match response.extensions().get::<Deadline>() {
+            Some(&Deadline(deadline)) => timeout_at(deadline, self.read_full_body(response))
+                .await
+                .unwrap_or(Err(Error::Timeout)),
+            None => self.read_full_body(response).await,
+        }
     }
 
     // private
 
-    /// Adds the Authorization HTTP header to a request if a credentials were supplied.
-    fn add_auth_header(&self, request: Builder) -> Builder {
-        if let Some(ref basic_auth) = self.basic_auth {
-            let auth = format!("{}:{}", basic_auth.username, basic_auth.password);
-            let header_value = format!("Basic {}", encode(&auth));
+    /// Reads the body of a response until its end, or until it exceeds the maximum response size.
+    async fn read_full_body(&self, response: Response<Body>) -> Result<Bytes, Error> {
+        let limit = match self.max_response_size {
+            Some(limit) => limit,
+            None => return Ok(hyper::body::to_bytes(response).await?),
+        };
 
-            request.header(AUTHORIZATION, header_value)
-        } else {
-            request
+        let content_length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+
+        if content_length.map_or(false, |length| length > limit as u64) {
+            return Err(Error::ResponseTooLarge { limit });
         }
-    }
 
-    /// Makes a request to etcd.
-    fn request(&self, method: Method, uri: Uri) -> ResponseFuture {
-        let request = Request::builder().method(method).uri(uri);
-        let request = self.add_auth_header(request);
-        self.hyper.request(request.body(Body::empty()).unwrap())
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk?;
+
+            if bytes.len() + chunk.len() > limit {
+                return Err(Error::ResponseTooLarge { limit });
+            }
+
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Ok(Bytes::from(bytes))
     }
 
-    /// Makes a request with an HTTP body to etcd.
-    fn request_with_body(&self, method: Method, uri: Uri, body: String) -> ResponseFuture {
-        let request = Request::builder()
-            .method(method)
-            .uri(uri)
-            .header(CONTENT_TYPE, "application/x-www-form-urlencoded");
+    /// Builds a request to etcd, with an HTTP body if one is given.
+    fn build_request(
+        &self,
+        method: Method,
+        uri: Uri,
+        body: Option<String>,
+        long_poll: bool,
+    ) -> Request<Body> {
+        let mut request = match body {
+            Some(body) => {
+                let mut request = Request::new(Body::from(body));
+                request.headers_mut().insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                );
+                request
+            }
+            None => Request::new(Body::empty()),
+        };
+
+        *request.method_mut() = method;
+        *request.uri_mut() = uri;
+
+        for (name, value) in self.headers.iter() {
+            request.headers_mut().insert(name, value.clone());
+        }
+
+        if long_poll {
+            request.extensions_mut().insert(LongPoll);
+        }
+
+        if self.non_idempotent {
+            request.extensions_mut().insert(NonIdempotent);
+        }
+
+        request
+    }
 
-        let request = self.add_auth_header(request);
+    /// Makes a request to etcd through the transport.
+    fn request(
+        &self,
+        method: Method,
+        uri: Uri,
+        body: Option<String>,
+        long_poll: bool,
+    ) -> impl Future<Output = Result<Response<Body>, Error>> {
+        let request = self.build_request(method, uri, body, long_poll);
 
-        self.hyper.request(request.body(Body::from(body)).unwrap())
+        send(self.transport.clone(), request)
     }
 }
@@ -6,12 +6,15 @@
 
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
 use std::time::Duration;
 
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
 use hyper::client::connect::Connect;
-use hyper::{StatusCode, Uri};
+use hyper::{Body, StatusCode, Uri};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde_derive::{Deserialize, Serialize};
-use serde_json;
 use std::future::Future;
 use tokio::time::timeout;
 use url::Url;
@@ -19,9 +22,11 @@ use url::Url;
 pub use crate::error::WatchError;
 pub use crate::options::ComparisonConditions;
 
-use crate::client::{Client, ClusterInfo, Response};
-use crate::error::{ApiError, Error};
-use crate::first_ok::{first_ok, Result};
+use crate::client::{build_url, Client, ClusterInfo, Response};
+use crate::coalesce::{InFlightReads, ReadKey};
+use crate::error::Error;
+use crate::first_ok::{first_ok, Idempotency, Result};
+use crate::nodes;
 use crate::options::{DeleteOptions, GetOptions as InternalGetOptions, SetOptions};
 use url::form_urlencoded::Serializer;
 
@@ -99,13 +104,26 @@ pub struct GetOptions {
     /// If true and the node is a directory, any child nodes returned will be sorted
     /// alphabetically.
     pub sort: bool,
+    /// How up to date the returned node must be.
+    pub consistency: ReadConsistency,
     /// If true, the etcd node serving the response will synchronize with the quorum before
-    /// returning the value.
-    ///
-    /// This is slower but avoids possibly stale data from being returned.
+    /// returning the value, as with `ReadConsistency::Quorum`, whatever `consistency` is.
+    #[deprecated(note = "use `consistency: ReadConsistency::Quorum` instead")]
     pub strong_consistency: bool,
 }
 
+impl GetOptions {
+    /// Returns the consistency the read is made with, taking `strong_consistency` into account.
+    #[allow(deprecated)]
+    fn read_consistency(&self) -> ReadConsistency {
+        if self.strong_consistency {
+            ReadConsistency::Quorum
+        } else {
+            self.consistency
+        }
+    }
+}
+
 /// Options for customizing the behavior of `kv::watch`.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 pub struct WatchOptions {
@@ -116,6 +134,114 @@ pub struct WatchOptions {
     pub recursive: bool,
     /// If given, the watch operation will time out if it's still waiting after the duration.
     pub timeout: Option<Duration>,
+    /// Which changes the watch operation may return, if no `index` is given.
+    pub consistency: ReadConsistency,
+}
+
+/// How up to date the data returned by a read must be.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ReadConsistency {
+    /// Whichever cluster member answers returns its own view of the data, which may be stale if
+    /// the member is lagging behind the rest of the cluster.
+    ///
+    /// Clients built with `ClientBuilder::monotonic_reads` treat this as `Monotonic`.
+    ///
+    /// A watch waits for the next change the answering member learns about.
+    #[default]
+    Serializable,
+    /// The cluster member answering synchronizes with the quorum of the cluster first, so the data
+    /// is up to date. This is slower, since the read goes through the Raft log via the leader.
+    ///
+    /// A watch first finds the current etcd index this way, and waits for the next change after
+    /// it.
+    Quorum,
+    /// The data is at least as recent as any the client has already observed. Answers from
+    /// cluster members lagging behind that are rejected, and the read is made to the next member.
+    ///
+    /// A watch waits for the first change after the highest etcd index the client has observed,
+    /// so it neither returns a change older than data the client has seen, nor misses one that
+    /// happened since.
+    Monotonic,
+}
+
+/// Characters that are percent-encoded in a segment of a key's path: all but the unreserved
+/// characters of RFC 3986.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// A validated key, which can be used in the URL of a key-value API call.
+///
+/// Keys are paths of segments separated by slashes. A leading slash is added if missing, and a
+/// trailing slash is allowed. Every function in this module validates its key this way, so
+/// `KeyPath` is only needed to check keys ahead of time.
+///
+/// Keys with empty segments, such as `/foo//bar`, or `.` or `..` segments are rejected, since etcd
+/// normalizes such paths and would operate on a different key than the one given. Any other
+/// characters are allowed, and are percent-encoded in the URL.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct KeyPath {
+    key: String,
+}
+
+impl KeyPath {
+    /// Validates a key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if etcd could not store the key as given.
+    pub fn new(key: &str) -> std::result::Result<Self, Error> {
+        let key = if key.starts_with('/') {
+            key.to_owned()
+        } else {
+            format!("/{}", key)
+        };
+
+        let path = if key.len() > 1 && key.ends_with('/') {
+            &key[1..key.len() - 1]
+        } else {
+            &key[1..]
+        };
+
+        if key != "/" {
+            for segment in path.split('/') {
+                let reason = match segment {
+                    "" => "it contains an empty path segment",
+                    "." | ".." => "it contains a relative path segment",
+                    _ => continue,
+                };
+
+                return Err(Error::InvalidKey(format!(
+                    "{:?} is not a valid key: {}",
+                    key, reason
+                )));
+            }
+        }
+
+        Ok(KeyPath { key })
+    }
+
+    /// Returns the key, with a leading slash.
+    pub fn as_str(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the key with each path segment percent-encoded, for use in a URL.
+    pub fn encoded(&self) -> String {
+        self.key
+            .split('/')
+            .map(|segment| utf8_percent_encode(segment, SEGMENT).to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+impl Display for KeyPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.key)
+    }
 }
 
 /// Deletes a node only if the given current value and/or current modified index match.
@@ -125,9 +251,9 @@ pub struct WatchOptions {
 /// * client: A `Client` to use to make the API call.
 /// * key: The name of the node to delete.
 /// * current_value: If given, the node must currently have this value for the operation to
-/// succeed.
+///   succeed.
 /// * current_modified_index: If given, the node must currently be at this modified index for the
-/// operation to succeed.
+///   operation to succeed.
 ///
 /// # Errors
 ///
@@ -164,9 +290,9 @@ where
 /// * value: The new value for the node.
 /// * ttl: If given, the node will expire after this many seconds.
 /// * current_value: If given, the node must currently have this value for the operation to
-/// succeed.
+///   succeed.
 /// * current_modified_index: If given, the node must currently be at this modified index for the
-/// operation to succeed.
+///   operation to succeed.
 ///
 /// # Errors
 ///
@@ -190,7 +316,7 @@ where
                 value: current_value,
                 modified_index: current_modified_index,
             }),
-            ttl: ttl,
+            ttl,
             value: Some(value),
             ..Default::default()
         },
@@ -255,7 +381,7 @@ where
         SetOptions {
             dir: Some(true),
             prev_exist: Some(false),
-            ttl: ttl,
+            ttl,
             ..Default::default()
         },
     )
@@ -294,7 +420,7 @@ where
         key,
         SetOptions {
             create_in_order: true,
-            ttl: ttl,
+            ttl,
             value: Some(value),
             ..Default::default()
         },
@@ -308,7 +434,7 @@ where
 /// * client: A `Client` to use to make the API call.
 /// * key: The name of the node to delete.
 /// * recursive: If true, and the key is a directory, the directory and all child key-value
-/// pairs and directories will be deleted as well.
+///   pairs and directories will be deleted as well.
 ///
 /// # Errors
 ///
@@ -360,6 +486,9 @@ where
 
 /// Gets the value of a node.
 ///
+/// If the client coalesces reads, this waits for an identical get in flight instead of making
+/// its own request. See `ClientBuilder::coalesce_reads`.
+///
 /// # Parameters
 ///
 /// * client: A `Client` to use to make the API call.
@@ -377,16 +506,73 @@ pub fn get<'a, C>(
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    raw_get(
+    let options = InternalGetOptions {
+        recursive: options.recursive,
+        sort: Some(options.sort),
+        consistency: options.read_consistency(),
+        ..Default::default()
+    };
+
+    async move {
+        match client.in_flight_reads() {
+            Some(in_flight_reads) if options.consistency != ReadConsistency::Quorum => {
+                coalesced_get(client, in_flight_reads, key, options).await
+            }
+            _ => raw_get(client, key, options).await,
+        }
+    }
+}
+
+/// Gets a node and the nodes under it as a stream, parsed while the response is being received.
+///
+/// Unlike `kv::get`, neither the response nor the tree of nodes it describes is ever held in memory
+/// at once, so this can read directories of any size. The client's maximum response size applies
+/// to the part of the response each node is parsed from instead of the whole response. Each node is
+/// yielded without its `nodes`, and each directory after the nodes under it, ending with the node
+/// of the key itself.
+///
+/// The response is parsed on a thread of Tokio's blocking pool, which waits whenever the stream
+/// falls behind by a few nodes. The stream should therefore be consumed without long pauses, or
+/// dropped, which stops the parsing and frees the thread.
+///
+/// # Parameters
+///
+/// * client: A `Client` to use to make the API call.
+/// * key: The name of the node to retrieve.
+/// * options: Options to customize the behavior of the operation. Nodes under subdirectories are
+///   only included if `recursive` is true.
+///
+/// # Errors
+///
+/// Fails if the key doesn't exist. An error while receiving or parsing the response, including
+/// `Error::ResponseTooLarge` for a node that exceeds the client's maximum response size, ends the
+/// stream after the nodes parsed before it.
+pub fn get_nodes<'a, C>(
+    client: &'a Client<C>,
+    key: &'a str,
+    options: GetOptions,
+) -> impl Stream<Item = std::result::Result<Node, Error>> + 'a
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    let limit = client.http_client().max_response_size();
+    let body = raw_get_body(
         client,
         key,
         InternalGetOptions {
             recursive: options.recursive,
             sort: Some(options.sort),
-            strong_consistency: options.strong_consistency,
+            consistency: options.read_consistency(),
             ..Default::default()
         },
-    )
+    );
+
+    stream::once(body)
+        .map(move |result| match result {
+            Ok(response) => nodes::parse(response.data, limit).left_stream(),
+            Err(error) => stream::once(future::ready(Err(error))).right_stream(),
+        })
+        .flatten()
 }
 
 /// Sets the value of a key-value pair.
@@ -416,7 +602,7 @@ where
         client,
         key,
         SetOptions {
-            ttl: ttl,
+            ttl,
             value: Some(value),
             ..Default::default()
         },
@@ -482,7 +668,7 @@ where
         key,
         SetOptions {
             dir: Some(true),
-            ttl: ttl,
+            ttl,
             ..Default::default()
         },
     )
@@ -514,7 +700,7 @@ where
         key,
         SetOptions {
             prev_exist: Some(true),
-            ttl: ttl,
+            ttl,
             value: Some(value),
             ..Default::default()
         },
@@ -549,7 +735,7 @@ where
         SetOptions {
             dir: Some(true),
             prev_exist: Some(true),
-            ttl: ttl,
+            ttl,
             ..Default::default()
         },
     )
@@ -580,19 +766,27 @@ pub async fn watch<C>(
 where
     C: Clone + Connect + Sync + Send + 'static,
 {
-    let work = raw_get(
-        client,
-        key,
-        InternalGetOptions {
-            recursive: options.recursive,
-            wait_index: options.index,
-            wait: true,
-            ..Default::default()
-        },
-    );
+    let work = async {
+        let wait_index = match options.index {
+            Some(index) => Some(index),
+            None => watch_start_index(client, key, options.consistency).await?,
+        };
+
+        raw_get(
+            client,
+            key,
+            InternalGetOptions {
+                recursive: options.recursive,
+                wait_index,
+                wait: true,
+                ..Default::default()
+            },
+        )
+        .await
+    };
 
     if let Some(duration) = options.timeout {
-        match timeout(duration.into(), work).await {
+        match timeout(duration, work).await {
             Ok(res) => res.map_err(WatchError::Other),
             Err(_) => Err(WatchError::Timeout),
         }
@@ -601,9 +795,45 @@ where
     }
 }
 
+/// Returns the etcd index a watch without an explicit index starts from, for the given
+/// consistency level.
+async fn watch_start_index<C>(
+    client: &Client<C>,
+    key: &str,
+    consistency: ReadConsistency,
+) -> std::result::Result<Option<u64>, Error>
+where
+    C: Clone + Connect + Sync + Send + 'static,
+{
+    let consistency = match consistency {
+        ReadConsistency::Serializable if client.cluster().monotonic_reads() => {
+            ReadConsistency::Monotonic
+        }
+        consistency => consistency,
+    };
+
+    match consistency {
+        ReadConsistency::Serializable => Ok(None),
+        ReadConsistency::Monotonic => Ok(client.etcd_index().map(|index| index + 1)),
+        ReadConsistency::Quorum => {
+            let options = InternalGetOptions {
+                consistency: ReadConsistency::Quorum,
+                ..Default::default()
+            };
+
+            match raw_get(client, key, options).await {
+                Ok(response) => Ok(response.cluster_info.etcd_index.map(|index| index + 1)),
+                // The key may not exist yet, but the error still tells the current index.
+                Err(Error::Api(ref error)) if error.error_code == 100 => Ok(Some(error.index + 1)),
+                Err(error) => Err(error),
+            }
+        }
+    }
+}
+
 /// Constructs the full URL for an API call.
-fn build_uri(endpoint: &Uri, path: &str) -> std::result::Result<Uri, http::uri::InvalidUri> {
-    format!("{}v2/keys{}", endpoint, path).parse()
+fn build_uri(endpoint: &Uri, key: &KeyPath) -> std::result::Result<Uri, http::uri::InvalidUri> {
+    build_url(endpoint, &format!("v2/keys{}", key.encoded()))
 }
 
 /// Handles all delete operations.
@@ -617,37 +847,36 @@ where
 {
     let mut query_pairs = HashMap::new();
 
-    if options.recursive.is_some() {
-        query_pairs.insert("recursive", format!("{}", options.recursive.unwrap()));
-    }
+    // A delete that was applied fails with a missing key, and a compare-and-delete fails its
+    // conditions, when it is made again.
+    let idempotency = Idempotency::NonIdempotent;
 
-    if options.dir.is_some() {
-        query_pairs.insert("dir", format!("{}", options.dir.unwrap()));
+    if let Some(recursive) = options.recursive {
+        query_pairs.insert("recursive", format!("{}", recursive));
     }
 
-    if options.conditions.is_some() {
-        let conditions = options.conditions.unwrap();
+    if let Some(dir) = options.dir {
+        query_pairs.insert("dir", format!("{}", dir));
+    }
 
+    if let Some(conditions) = options.conditions {
         if conditions.is_empty() {
-            return Err(vec![Error::InvalidConditions]);
+            return Err(Error::InvalidConditions);
         }
 
-        if conditions.modified_index.is_some() {
-            query_pairs.insert(
-                "prevIndex",
-                format!("{}", conditions.modified_index.unwrap()),
-            );
+        if let Some(modified_index) = conditions.modified_index {
+            query_pairs.insert("prevIndex", format!("{}", modified_index));
         }
 
-        if conditions.value.is_some() {
-            query_pairs.insert("prevValue", conditions.value.unwrap().to_owned());
+        if let Some(value) = conditions.value {
+            query_pairs.insert("prevValue", value.to_owned());
         }
     }
 
-    let http_client = client.http_client().clone();
-    let key = key.to_string();
+    let http_client = client.http_client().with_idempotency(&idempotency);
+    let key = KeyPath::new(key)?;
 
-    first_ok(client.endpoints().to_vec(), move |endpoint| {
+    first_ok(client.cluster(), idempotency, move |endpoint| {
         let http_client = http_client.clone();
         let query_pairs = query_pairs.clone();
         let key = key.clone();
@@ -659,17 +888,14 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
             if status == StatusCode::OK {
                 match serde_json::from_slice::<KeyValueInfo>(&body) {
                     Ok(data) => Ok(Response { data, cluster_info }),
                     Err(error) => Err(Error::Serialization(error)),
                 }
             } else {
-                match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                }
+                Err(Error::from_response(status, cluster_info, &body))
             }
         }
     })
@@ -685,56 +911,200 @@ async fn raw_get<C>(
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    let mut query_pairs = HashMap::new();
-
-    query_pairs.insert("recursive", format!("{}", options.recursive));
-
-    if options.sort.is_some() {
-        query_pairs.insert("sorted", format!("{}", options.sort.unwrap()));
-    }
-
-    if options.wait {
-        query_pairs.insert("wait", "true".to_owned());
-    }
-
-    if options.wait_index.is_some() {
-        query_pairs.insert("waitIndex", format!("{}", options.wait_index.unwrap()));
-    }
-
+    let cluster = client.cluster();
+    let consistency = read_consistency(client, options.consistency);
+    let query_pairs = get_query_pairs(&options, consistency);
     let http_client = client.http_client().clone();
-    let key = key.to_string();
+    let key = KeyPath::new(key)?;
+    let wait = options.wait;
 
-    first_ok(client.endpoints().to_vec(), move |endpoint| {
+    first_ok(client.cluster(), Idempotency::Idempotent, move |endpoint| {
         let http_client = http_client.clone();
         let key = key.clone();
         let query_pairs = query_pairs.clone();
 
+        // Watches wait for a change after a given index, so only plain reads can be stale.
+        let min_index = if !wait && consistency == ReadConsistency::Monotonic {
+            cluster.etcd_index()
+        } else {
+            None
+        };
+
         async move {
             let url =
                 Url::parse_with_params(&build_uri(&endpoint, &key)?.to_string(), query_pairs)?;
             let uri = url.to_string().parse()?;
-            let response = http_client.get(uri).await?;
+            let response = if wait {
+                http_client.long_poll(uri).await?
+            } else {
+                http_client.get(uri).await?
+            };
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
-            if status == StatusCode::OK {
+            let result = if status == StatusCode::OK {
                 match serde_json::from_slice::<KeyValueInfo>(&body) {
                     Ok(data) => Ok(Response { data, cluster_info }),
                     Err(error) => Err(Error::Serialization(error)),
                 }
             } else {
-                match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                }
-            }
+                Err(Error::from_response(status, cluster_info, &body))
+            };
+
+            check_stale(result, min_index)
         }
     })
     .await
 }
 
+/// Handles gets that wait for an identical get in flight, if there is one, instead of making their
+/// own request.
+async fn coalesced_get<C>(
+    client: &Client<C>,
+    in_flight_reads: &InFlightReads,
+    key: &str,
+    options: InternalGetOptions,
+) -> Result<KeyValueInfo>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    let consistency = read_consistency(client, options.consistency);
+
+    // A monotonic read must not be answered by a read that started before a newer etcd index
+    // was observed.
+    let min_index = if consistency == ReadConsistency::Monotonic {
+        client.cluster().etcd_index()
+    } else {
+        None
+    };
+
+    let read_key = ReadKey::new(
+        key,
+        &get_query_pairs(&options, consistency),
+        client.http_client().headers(),
+        min_index,
+    );
+    let client = client.clone();
+    let key = key.to_owned();
+
+    in_flight_reads
+        .read(
+            read_key,
+            async move { raw_get(&client, &key, options).await },
+        )
+        .await
+}
+
+/// Handles gets whose nodes are parsed from the response while it is being received, returning
+/// the body of a successful response before reading it.
+async fn raw_get_body<C>(client: &Client<C>, key: &str, options: InternalGetOptions) -> Result<Body>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    let cluster = client.cluster();
+    let consistency = read_consistency(client, options.consistency);
+    let query_pairs = get_query_pairs(&options, consistency);
+    let http_client = client.http_client().clone();
+    let key = KeyPath::new(key)?;
+
+    first_ok(client.cluster(), Idempotency::Idempotent, move |endpoint| {
+        let http_client = http_client.clone();
+        let key = key.clone();
+        let query_pairs = query_pairs.clone();
+
+        let min_index = if consistency == ReadConsistency::Monotonic {
+            cluster.etcd_index()
+        } else {
+            None
+        };
+
+        async move {
+            let url =
+                Url::parse_with_params(&build_uri(&endpoint, &key)?.to_string(), query_pairs)?;
+            let response = http_client.get(url.to_string().parse()?).await?;
+
+            let status = response.status();
+            let cluster_info = ClusterInfo::from(response.headers());
+
+            let result = if status == StatusCode::OK {
+                Ok(Response {
+                    data: response.into_body(),
+                    cluster_info,
+                })
+            } else {
+                let body = http_client.read_body(response).await?;
+                Err(Error::from_response(status, cluster_info, &body))
+            };
+
+            check_stale(result, min_index)
+        }
+    })
+    .await
+}
+
+/// Returns the consistency a read is made with, which is `Monotonic` for reads that don't ask for
+/// one by clients with monotonic reads.
+fn read_consistency<C>(client: &Client<C>, consistency: ReadConsistency) -> ReadConsistency
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    match consistency {
+        ReadConsistency::Serializable if client.cluster().monotonic_reads() => {
+            ReadConsistency::Monotonic
+        }
+        consistency => consistency,
+    }
+}
+
+/// Returns the query parameters of a get.
+fn get_query_pairs(
+    options: &InternalGetOptions,
+    consistency: ReadConsistency,
+) -> HashMap<&'static str, String> {
+    let mut query_pairs = HashMap::new();
+
+    query_pairs.insert("recursive", format!("{}", options.recursive));
+
+    if consistency == ReadConsistency::Quorum {
+        query_pairs.insert("quorum", "true".to_owned());
+    }
+
+    if let Some(sort) = options.sort {
+        query_pairs.insert("sorted", format!("{}", sort));
+    }
+
+    if options.wait {
+        query_pairs.insert("wait", "true".to_owned());
+    }
+
+    if let Some(wait_index) = options.wait_index {
+        query_pairs.insert("waitIndex", format!("{}", wait_index));
+    }
+
+    query_pairs
+}
+
+/// Fails a read with `Error::StaleRead` if it answered from before the given etcd index.
+fn check_stale<T>(result: Result<T>, min_index: Option<u64>) -> Result<T> {
+    if let Some(min_index) = min_index {
+        // A missing key is stale too, if the member hasn't caught up with its creation.
+        let index = match result {
+            Ok(ref response) => response.cluster_info.etcd_index,
+            Err(Error::Api(ref error)) => Some(error.index),
+            Err(_) => None,
+        };
+
+        match index {
+            Some(index) if index < min_index => return Err(Error::StaleRead { index, min_index }),
+            _ => {}
+        }
+    }
+
+    result
+}
+
 /// Handles all set operations.
 async fn raw_set<'a, C>(
     client: &Client<C>,
@@ -788,7 +1158,7 @@ where
 
     if let Some(conditions) = &options.conditions {
         if conditions.is_empty() {
-            return Err(vec![Error::InvalidConditions]);
+            return Err(Error::InvalidConditions);
         }
 
         if let Some(modified_index) = conditions.modified_index {
@@ -800,11 +1170,22 @@ where
         }
     }
 
-    let http_client = client.http_client().clone();
-    let key = key.to_string();
+    // Creating a key in order adds a new key each time it is applied, and a conditional write that
+    // was applied fails its conditions when it is made again.
+    let idempotency = if options.create_in_order
+        || options.conditions.is_some()
+        || options.prev_exist == Some(false)
+    {
+        Idempotency::NonIdempotent
+    } else {
+        Idempotency::Idempotent
+    };
+
+    let http_client = client.http_client().with_idempotency(&idempotency);
+    let key = KeyPath::new(key)?;
     let create_in_order = options.create_in_order;
 
-    first_ok(client.endpoints().to_vec(), move |endpoint| {
+    first_ok(client.cluster(), idempotency, move |endpoint| {
         let http_client = http_client.clone();
         let key = key.clone();
         let mut ser = Serializer::new(String::new());
@@ -821,7 +1202,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             match status {
                 StatusCode::CREATED | StatusCode::OK => {
@@ -830,10 +1211,7 @@ where
                         Err(error) => Err(Error::Serialization(error)),
                     }
                 }
-                _ => match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                },
+                _ => Err(Error::from_response(status, cluster_info, &body)),
             }
         }
     })
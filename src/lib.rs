@@ -12,16 +12,31 @@
 //!
 //! # Usage
 //!
-//! `Client` is an HTTP client required for all API calls. It can be constructed to use HTTP or
-//! HTTPS, and supports authenticating to the etcd cluster via HTTP basic authentication (username
-//! and password) and/or X.509 client certificates.
+//! `Client` is an HTTP client required for all API calls. It can be constructed to use HTTP,
+//! HTTPS, or Unix domain sockets (see the `unix` module), and supports authenticating to the etcd
+//! cluster via HTTP basic authentication (username and password) and/or X.509 client
+//! certificates (see the `tls` module). `ClientBuilder` additionally configures request and
+//! connection timeouts, TCP keepalive, connection pooling, and retries, and can take credentials
+//! that are rotated while the client is in use from the `credentials` module. The HTTP requests
+//! of API calls go through a stack of tower services that can be extended with layers, as
+//! described in the `middleware` module. The connector type of a client can be hidden by
+//! converting it into a `DynClient`, as described in the `connector` module.
 //!
 //! To get basic information about the health and versions of etcd running in a cluster, use the
 //! `Client::health` and `Client::versions` methods, respectively. All other API calls are made by
 //! passing a `Client` reference to the functions in the `auth`, `kv`, `members`, and `stats`
 //! modules. These modules contain functions for API calls to the authentication and authorization
 //! API, the primary key-value store API, the cluster membership API, and statistics API,
-//! respectively.
+//! respectively. Code that only uses the key-value API can depend on the `store::KeyValueStore`
+//! trait instead, which is also implemented by an in-memory store for tests.
+//!
+//! When a client is configured with several cluster members, API calls that only need a single
+//! member to answer are routed by an endpoint selector, which can be chosen from the strategies in
+//! the `selector` module. The client's endpoints can be kept up to date with the cluster's current
+//! members using the `discovery` module, and members that keep failing can be skipped until they
+//! recover using the circuit breakers and health probing in the `health` module. Members that need
+//! their own TLS configuration, credentials, weight, or path prefix, including a mix of HTTP and
+//! HTTPS members, are described with the `endpoint` module.
 //!
 //! # Examples
 //!
@@ -33,8 +48,8 @@
 //!
 //! async fn usage() {
 //!     // Create a client to access a single cluster member. Addresses of multiple cluster
-//!     // members can be provided and the client will try each one in sequence until it
-//!     // receives a successful response.
+//!     // members can be provided and the client will try each one in sequence until one of
+//!     // them answers.
 //!     let client = Client::new(&["http://etcd.example.com:2379"], None).unwrap();
 //!
 //!     // Set the key "/foo" to the value "bar" with no expiration.
@@ -56,22 +71,53 @@
 //!
 //! # Cargo features
 //!
-//! Crate `etcd` has one Cargo feature, `tls`, which adds HTTPS support via the `Client::https`
-//! constructor. This feature is enabled by default.
+//! Crate `etcd` has the following Cargo features:
+//!
+//! * `blocking`: Adds the `blocking` module, with versions of the API calls that block the calling
+//!   thread instead of returning futures, for callers that don't run a Tokio runtime.
+//! * `tls`: Adds HTTPS support via the `Client::https` and `Client::https_with_config`
+//!   constructors. This feature is enabled by default.
+//! * `rustls`: Adds HTTPS support using rustls instead of the platform's native TLS library, via
+//!   the `rustls` module and the `Client::https_rustls` and `Client::https_rustls_with_config`
+//!   constructors. Unlike `tls`, this feature does not depend on OpenSSL on any platform.
+//! * `srv`: Adds `discovery::DnsResolver`, which looks up the DNS SRV records of a cluster using
+//!   the system's DNS configuration.
 #![deny(missing_debug_implementations, missing_docs, warnings)]
 
-pub use crate::client::{BasicAuth, Client, ClusterInfo, Health, Response};
+pub use crate::client::{
+    BasicAuth, Client, ClientBuilder, ClusterInfo, DynClient, Health, Response,
+};
 pub use crate::error::{ApiError, Error};
 pub use crate::version::VersionInfo;
 
 pub mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod connector;
+pub mod credentials;
+pub mod discovery;
+pub mod endpoint;
+pub mod health;
 pub mod kv;
 pub mod members;
+pub mod middleware;
+#[cfg(feature = "rustls")]
+pub mod rustls;
+pub mod selector;
 pub mod stats;
+pub mod store;
+#[cfg(any(feature = "tls", feature = "rustls"))]
+pub mod tls;
+#[cfg(unix)]
+pub mod unix;
 
 mod client;
+mod cluster;
+mod coalesce;
 mod error;
 mod first_ok;
 mod http;
+mod nodes;
 mod options;
+mod task;
 mod version;
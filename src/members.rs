@@ -2,15 +2,15 @@
 //!
 //! These API endpoints are used to manage cluster membership.
 
+use futures::future::FutureExt;
 use hyper::client::connect::Connect;
 use hyper::{StatusCode, Uri};
 use serde_derive::{Deserialize, Serialize};
-use serde_json;
 use std::future::Future;
 
-use crate::client::{Client, ClusterInfo, Response};
-use crate::error::{ApiError, Error};
-use crate::first_ok::{first_ok, Result};
+use crate::client::{build_url, Client, ClusterInfo, Response};
+use crate::error::Error;
+use crate::first_ok::{first_ok, Idempotency, Result};
 
 /// An etcd server that is a member of a cluster.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -56,12 +56,38 @@ where
 
     let body = match serde_json::to_string(&peer_urls) {
         Ok(body) => body,
-        Err(error) => return Err(vec![Error::Serialization(error)]),
+        Err(error) => return Err(Error::Serialization(error)),
     };
 
-    let http_client = client.http_client().clone();
+    // If an attempt fails without a clear outcome, the member list shows whether it was applied.
+    let verify_client = client.clone();
+    let verify_peer_urls = peer_urls.peer_urls.clone();
+    let idempotency = Idempotency::Verifiable(Box::new(move || {
+        let client = verify_client.clone();
+        let peer_urls = verify_peer_urls.clone();
+
+        async move {
+            let response = list(&client).await?;
+            let added = response
+                .data
+                .iter()
+                .any(|member| same_urls(&member.peer_urls, &peer_urls));
+
+            if added {
+                Ok(Some(Response {
+                    data: (),
+                    cluster_info: response.cluster_info,
+                }))
+            } else {
+                Ok(None)
+            }
+        }
+        .boxed()
+    }));
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    let http_client = client.http_client().with_idempotency(&idempotency);
+
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
         let body = body.clone();
 
@@ -70,7 +96,7 @@ where
             let response = http_client.post(uri, body).await?;
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::CREATED {
                 Ok(Response {
@@ -78,10 +104,7 @@ where
                     cluster_info,
                 })
             } else {
-                match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                }
+                Err(Error::from_response(status, cluster_info, &body))
             }
         }
     })
@@ -98,9 +121,32 @@ pub fn delete<C>(client: &Client<C>, id: String) -> impl Future<Output = Result<
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    let http_client = client.http_client().clone();
+    // A delete that was applied fails with a missing member when it is made again, so if an
+    // attempt fails without a clear outcome, the member list shows whether it was applied.
+    let verify_client = client.clone();
+    let verify_id = id.clone();
+    let idempotency = Idempotency::Verifiable(Box::new(move || {
+        let client = verify_client.clone();
+        let id = verify_id.clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+        async move {
+            let response = list(&client).await?;
+
+            if response.data.iter().any(|member| member.id == id) {
+                Ok(None)
+            } else {
+                Ok(Some(Response {
+                    data: (),
+                    cluster_info: response.cluster_info,
+                }))
+            }
+        }
+        .boxed()
+    }));
+
+    let http_client = client.http_client().with_idempotency(&idempotency);
+
+    first_ok(client.cluster(), idempotency, move |member| {
         let http_client = http_client.clone();
         let id = id.clone();
 
@@ -110,7 +156,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::NO_CONTENT {
                 Ok(Response {
@@ -118,10 +164,7 @@ where
                     cluster_info,
                 })
             } else {
-                match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                }
+                Err(Error::from_response(status, cluster_info, &body))
             }
         }
     })
@@ -138,7 +181,7 @@ where
 {
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), Idempotency::Idempotent, move |member| {
         let http_client = http_client.clone();
 
         async move {
@@ -147,7 +190,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::OK {
                 match serde_json::from_slice::<ListResponse>(&body) {
@@ -158,10 +201,7 @@ where
                     Err(error) => Err(Error::Serialization(error)),
                 }
             } else {
-                match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                }
+                Err(Error::from_response(status, cluster_info, &body))
             }
         }
     })
@@ -182,12 +222,12 @@ where
 
     let body = match serde_json::to_string(&peer_urls) {
         Ok(body) => body,
-        Err(error) => return Err(vec![Error::Serialization(error)]),
+        Err(error) => return Err(Error::Serialization(error)),
     };
 
     let http_client = client.http_client().clone();
 
-    first_ok(client.endpoints().to_vec(), move |member| {
+    first_ok(client.cluster(), Idempotency::Idempotent, move |member| {
         let body = body.clone();
         let http_client = http_client.clone();
         let id = id.clone();
@@ -198,7 +238,7 @@ where
 
             let status = response.status();
             let cluster_info = ClusterInfo::from(response.headers());
-            let body = hyper::body::to_bytes(response).await?;
+            let body = http_client.read_body(response).await?;
 
             if status == StatusCode::NO_CONTENT {
                 Ok(Response {
@@ -206,17 +246,24 @@ where
                     cluster_info,
                 })
             } else {
-                match serde_json::from_slice::<ApiError>(&body) {
-                    Ok(error) => Err(Error::Api(error)),
-                    Err(error) => Err(Error::Serialization(error)),
-                }
+                Err(Error::from_response(status, cluster_info, &body))
             }
         }
     })
     .await
 }
 
+/// Returns whether two lists contain the same URLs, regardless of order.
+fn same_urls(a: &[String], b: &[String]) -> bool {
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    a.sort();
+    b.sort();
+
+    a == b
+}
+
 /// Constructs the full URL for an API call.
 fn build_uri(endpoint: &Uri, path: &str) -> std::result::Result<Uri, http::uri::InvalidUri> {
-    format!("{}v2/members{}", endpoint, path).parse()
+    build_url(endpoint, &format!("v2/members{}", path))
 }
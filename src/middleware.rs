@@ -0,0 +1,514 @@
+//! Middleware around the HTTP requests a client makes.
+//!
+//! A client makes the HTTP requests of its API calls through a `Transport`, a tower `Service` that
+//! sends a request to a single cluster member and returns the member's response. The client's HTTP
+//! basic authentication, request timeout, and retries are layers of this service, and more layers
+//! can be added inside or outside them with `ClientBuilder::layer` and `ClientBuilder::outer_layer`,
+//! such as to log or modify requests, limit their rate, or answer them without a cluster in tests.
+//!
+//! From the outside in, a request to a cluster member passes through:
+//!
+//! 1. The layers added with `ClientBuilder::outer_layer`, which see the request once.
+//! 2. Retries, which make the request again as described in `ClientBuilder::max_retries`.
+//! 3. The request timeout, which covers each attempt and the reading of its response body.
+//! 4. The Authorization header, from the client's or the endpoint's credentials.
+//! 5. The client's default headers, such as User-Agent.
+//! 6. The layers added with `ClientBuilder::layer`, which see every attempt of the request, and
+//!    whose time counts toward the request timeout.
+//! 7. The HTTP client.
+//!
+//! Within each group of layers, the first layer added is the outermost. Choosing which cluster
+//! member a request is sent to, and failing over to the next one, happens before the transport is
+//! called.
+//!
+//! Services in the stack fail with `Error`. Layers can fail a request with `Error::Middleware`.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use base64::encode;
+use futures::future::{poll_fn, BoxFuture, FutureExt};
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use hyper::client::connect::Connect;
+use hyper::{Body, Client as Hyper, Request, Response};
+use tokio::time::{delay_for, timeout_at, Instant};
+
+pub use tower_layer::Layer;
+pub use tower_service::Service;
+
+use crate::credentials::CredentialsProvider;
+use crate::error::Error;
+
+/// The service that sends the HTTP requests of a client's API calls.
+///
+/// A `Transport` is a boxed, cloneable tower `Service`, so layers can wrap it without naming the
+/// types of the services inside.
+pub struct Transport {
+    inner: Box<dyn CloneService>,
+}
+
+impl Transport {
+    /// Constructs a new `Transport` from a service.
+    pub fn new<S>(service: S) -> Self
+    where
+        S: Service<Request<Body>, Response = Response<Body>, Error = Error>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        Transport {
+            inner: Box::new(service),
+        }
+    }
+}
+
+impl Clone for Transport {
+    fn clone(&self) -> Self {
+        Transport {
+            inner: self.inner.clone_box(),
+        }
+    }
+}
+
+impl Debug for Transport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Transport").finish()
+    }
+}
+
+impl Service<Request<Body>> for Transport {
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Response<Body>, Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+/// An object-safe, cloneable version of the services a `Transport` can be made from.
+trait CloneService: Send + Sync {
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>>;
+
+    fn call(&mut self, request: Request<Body>)
+        -> BoxFuture<'static, Result<Response<Body>, Error>>;
+
+    fn clone_box(&self) -> Box<dyn CloneService>;
+}
+
+impl<S> CloneService for S
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Error>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Service::poll_ready(self, cx)
+    }
+
+    fn call(
+        &mut self,
+        request: Request<Body>,
+    ) -> BoxFuture<'static, Result<Response<Body>, Error>> {
+        Service::call(self, request).boxed()
+    }
+
+    fn clone_box(&self) -> Box<dyn CloneService> {
+        Box::new(self.clone())
+    }
+}
+
+/// Sends a request through a service once it is ready.
+pub(crate) async fn send<S>(mut service: S, request: Request<Body>) -> Result<Response<Body>, Error>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Error>,
+{
+    poll_fn(|cx| service.poll_ready(cx)).await?;
+    service.call(request).await
+}
+
+/// The layers added with `ClientBuilder::layer` or `ClientBuilder::outer_layer`, outermost first.
+#[derive(Clone, Default)]
+pub(crate) struct Layers {
+    layers: Vec<Arc<dyn Fn(Transport) -> Transport + Send + Sync>>,
+}
+
+impl Layers {
+    /// Adds a layer inside the ones added before.
+    pub fn push<L>(&mut self, layer: L)
+    where
+        L: Layer<Transport> + Send + Sync + 'static,
+        L::Service: Service<Request<Body>, Response = Response<Body>, Error = Error>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<Request<Body>>>::Future: Send + 'static,
+    {
+        self.layers
+            .push(Arc::new(move |inner| Transport::new(layer.layer(inner))));
+    }
+
+    /// Wraps a transport in the layers.
+    pub fn apply(&self, transport: Transport) -> Transport {
+        self.layers
+            .iter()
+            .rev()
+            .fold(transport, |transport, layer| layer(transport))
+    }
+}
+
+impl Debug for Layers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Layers")
+            .field("len", &self.layers.len())
+            .finish()
+    }
+}
+
+/// Marks a request that may wait indefinitely for a response, such as a watch, so that the request
+/// timeout does not apply to it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LongPoll;
+
+/// The time by which the body of a response must have been read, set by the request timeout.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Deadline(pub Instant);
+
+/// Marks a request that could be applied twice if it is made again after reaching the member, so
+/// that it is only retried when the failed attempt certainly did not reach it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct NonIdempotent;
+
+/// The service at the bottom of the stack, which makes requests with a hyper client.
+#[derive(Clone, Debug)]
+pub(crate) struct HttpService<C>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    hyper: Hyper<C>,
+}
+
+impl<C> HttpService<C>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    /// Constructs a new `HttpService`.
+    pub fn new(hyper: Hyper<C>) -> Self {
+        HttpService { hyper }
+    }
+}
+
+impl<C> Service<Request<Body>> for HttpService<C>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Response<Body>, Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        self.hyper
+            .request(request)
+            .map(|result| result.map_err(Error::Http))
+            .boxed()
+    }
+}
+
+/// The credentials providers a client authenticates with.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Credentials {
+    client: Option<Arc<dyn CredentialsProvider>>,
+    endpoints: Arc<Vec<(String, Arc<dyn CredentialsProvider>)>>,
+}
+
+impl Credentials {
+    /// Constructs new `Credentials` from the client-wide provider and the providers of endpoints
+    /// that don't use it, keyed by the endpoint's URL.
+    pub fn new(
+        client: Option<Arc<dyn CredentialsProvider>>,
+        mut endpoints: Vec<(String, Arc<dyn CredentialsProvider>)>,
+    ) -> Self {
+        // The longest matching URL wins when endpoints only differ in their path.
+        endpoints.sort_by_key(|(endpoint, _)| std::cmp::Reverse(endpoint.len()));
+
+        Credentials {
+            client,
+            endpoints: Arc::new(endpoints),
+        }
+    }
+
+    /// Returns the provider for the endpoint a request is made to, if any.
+    fn for_uri(&self, uri: &str) -> Option<&Arc<dyn CredentialsProvider>> {
+        self.endpoints
+            .iter()
+            .find(|(endpoint, _)| uri.starts_with(endpoint.as_str()))
+            .map(|(_, credentials)| credentials)
+            .or(self.client.as_ref())
+    }
+}
+
+/// Adds the Authorization HTTP header to requests without one if the credentials provider of the
+/// endpoint they are made to supplies credentials.
+#[derive(Clone, Debug)]
+pub(crate) struct Auth<S> {
+    credentials: Credentials,
+    inner: S,
+}
+
+impl<S> Auth<S> {
+    /// Constructs a new `Auth` around a service.
+    pub fn new(inner: S, credentials: Credentials) -> Self {
+        Auth { credentials, inner }
+    }
+}
+
+impl<S> Service<Request<Body>> for Auth<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Error>,
+{
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        if request.headers().contains_key(AUTHORIZATION) {
+            return self.inner.call(request);
+        }
+
+        let basic_auth = self
+            .credentials
+            .for_uri(&request.uri().to_string())
+            .and_then(|credentials| credentials.basic_auth());
+
+        if let Some(basic_auth) = basic_auth {
+            let auth = format!("{}:{}", basic_auth.username, basic_auth.password);
+
+            if let Ok(header_value) = HeaderValue::from_str(&format!("Basic {}", encode(&auth))) {
+                request.headers_mut().insert(AUTHORIZATION, header_value);
+            }
+        }
+
+        self.inner.call(request)
+    }
+}
+
+/// Adds the client's default HTTP headers to requests that don't set headers of the same name.
+#[derive(Clone, Debug)]
+pub(crate) struct DefaultHeaders<S> {
+    headers: Arc<HeaderMap>,
+    inner: S,
+}
+
+impl<S> DefaultHeaders<S> {
+    /// Constructs a new `DefaultHeaders` around a service.
+    pub fn new(inner: S, headers: HeaderMap) -> Self {
+        DefaultHeaders {
+            headers: Arc::new(headers),
+            inner,
+        }
+    }
+}
+
+impl<S> Service<Request<Body>> for DefaultHeaders<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Error>,
+{
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let headers = request.headers_mut();
+
+        for name in self.headers.keys() {
+            if headers.contains_key(name) {
+                continue;
+            }
+
+            for value in self.headers.get_all(name) {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+
+        self.inner.call(request)
+    }
+}
+
+/// Fails requests that don't receive a response within the request timeout with
+/// `Error::Timeout`, unless they are marked as `LongPoll`.
+///
+/// The deadline also applies to reading the body of the response, so it is attached to the
+/// response as a `Deadline` for the client to read the body by.
+#[derive(Clone, Debug)]
+pub(crate) struct Timeout<S> {
+    duration: Option<Duration>,
+    inner: S,
+}
+
+impl<S> Timeout<S> {
+    /// Constructs a new `Timeout` around a service.
+    pub fn new(inner: S, duration: Option<Duration>) -> Self {
+        Timeout { duration, inner }
+    }
+}
+
+impl<S> Service<Request<Body>> for Timeout<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Error>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Response<Body>, Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let duration = match self.duration {
+            Some(_) if request.extensions().get::<LongPoll>().is_some() => None,
+            duration => duration,
+        };
+        let deadline = duration.map(|duration| Instant::now() + duration);
+        let response = self.inner.call(request);
+
+        async move {
+            match deadline {
+                Some(deadline) => match timeout_at(deadline, response).await {
+                    Ok(Ok(mut response)) => {
+                        response.extensions_mut().insert(Deadline(deadline));
+                        Ok(response)
+                    }
+                    Ok(Err(error)) => Err(error),
+                    Err(_) => Err(Error::Timeout),
+                },
+                None => response.await,
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Makes a request to the same member again when it could not be reached, timed out, or answered
+/// with a server error, up to the client's `max_retries` times.
+///
+/// The first retry waits for the client's `retry_backoff`, and each later one twice as long as
+/// the one before, so that a struggling member isn't sent the request again right away.
+///
+/// Requests marked as `NonIdempotent` are only made again when the failed attempt certainly did
+/// not reach the member. The last response or error is returned when the retries are exhausted.
+#[derive(Clone, Debug)]
+pub(crate) struct Retry<S> {
+    backoff: Duration,
+    inner: S,
+    max_retries: usize,
+}
+
+impl<S> Retry<S> {
+    /// Constructs a new `Retry` around a service.
+    pub fn new(inner: S, max_retries: usize, backoff: Duration) -> Self {
+        Retry {
+            backoff,
+            inner,
+            max_retries,
+        }
+    }
+}
+
+impl<S> Service<Request<Body>> for Retry<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Error> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Response<Body>, Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if self.max_retries == 0 {
+            return self.inner.call(request).boxed();
+        }
+
+        // The service that was polled ready makes the first attempt.
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        let max_retries = self.max_retries;
+        let mut backoff = self.backoff;
+
+        async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await.map_err(Error::Http)?;
+            let long_poll = parts.extensions.get::<LongPoll>().is_some();
+            let non_idempotent = parts.extensions.get::<NonIdempotent>().is_some();
+            let mut retries = 0;
+
+            loop {
+                let mut request = Request::new(Body::from(body.clone()));
+                *request.method_mut() = parts.method.clone();
+                *request.uri_mut() = parts.uri.clone();
+                *request.version_mut() = parts.version;
+                *request.headers_mut() = parts.headers.clone();
+
+                if long_poll {
+                    request.extensions_mut().insert(LongPoll);
+                }
+
+                if non_idempotent {
+                    request.extensions_mut().insert(NonIdempotent);
+                }
+
+                if retries > 0 {
+                    delay_for(backoff).await;
+                    backoff *= 2;
+
+                    poll_fn(|cx| inner.poll_ready(cx)).await?;
+                }
+
+                let result = inner.call(request).await;
+
+                let retry = match result {
+                    Ok(ref response) => !non_idempotent && response.status().is_server_error(),
+                    Err(ref error) => {
+                        error.should_fail_over() && (!non_idempotent || error.is_connect())
+                    }
+                };
+
+                if !retry || retries == max_retries {
+                    return result;
+                }
+
+                retries += 1;
+            }
+        }
+        .boxed()
+    }
+}
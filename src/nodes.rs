@@ -0,0 +1,262 @@
+//! Incremental parsing of key-value API responses into a stream of nodes.
+//!
+//! The body of a response is parsed on a blocking task while it is being received, and each node
+//! is sent on as soon as it has been parsed, without the nodes under it, so that neither the body
+//! nor the tree of nodes it describes is ever held in memory at once. The client's maximum response
+//! size limits the part of the body each node is parsed from instead.
+
+use std::cell::Cell;
+use std::fmt::{self, Formatter};
+use std::io::{Error as IoError, ErrorKind, Read};
+use std::rc::Rc;
+
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use futures::executor::block_on;
+use futures::sink::SinkExt;
+use futures::stream::{Stream, StreamExt};
+use hyper::body::{Bytes, HttpBody};
+use hyper::Body;
+use serde::de::{
+    DeserializeSeed, Deserializer, Error as DeError, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use serde_json::{Map, Value};
+use tokio::task::spawn_blocking;
+
+use crate::error::Error;
+use crate::kv::Node;
+
+/// How many parsed nodes, or received chunks of the body, are buffered before the parser, or
+/// the task receiving the body, waits for them to be consumed.
+const BUFFER: usize = 16;
+
+/// Parses the body of a successful key-value API response into a stream of its nodes.
+///
+/// Each directory follows the nodes under it, since etcd sends the indexes of a directory after
+/// its children. Fails with `Error::ResponseTooLarge` if more than `limit` bytes of the body are
+/// read between two nodes. Must be called from within a Tokio runtime.
+pub(crate) fn parse(body: Body, limit: Option<usize>) -> impl Stream<Item = Result<Node, Error>> {
+    let (chunk_sender, chunks) = channel(BUFFER);
+    let (node_sender, nodes) = channel(BUFFER);
+
+    tokio::spawn(receive(body, chunk_sender));
+
+    spawn_blocking(move || {
+        let unsent = Rc::new(Cell::new(0));
+        let mut reader = ChunkReader {
+            chunk: Bytes::new(),
+            chunks,
+            error: None,
+            limit,
+            unsent: unsent.clone(),
+        };
+        let mut sender = node_sender.clone();
+
+        let result = {
+            let mut deserializer = serde_json::Deserializer::from_reader(&mut reader);
+
+            ResponseSeed {
+                sender: node_sender,
+                unsent,
+            }
+            .deserialize(&mut deserializer)
+            .and_then(|()| deserializer.end())
+        };
+
+        let error = match (reader.error.take(), result) {
+            (Some(error), _) => error,
+            (None, Ok(())) => return,
+            (None, Err(error)) => Error::Serialization(error),
+        };
+
+        // The stream is gone if sending fails, which is also why parsing stopped.
+        block_on(sender.send(Err(error))).ok();
+    });
+
+    nodes
+}
+
+/// Sends the chunks of a body to the parser, until the body ends or the parser is gone.
+async fn receive(mut body: Body, mut chunks: Sender<Result<Bytes, hyper::Error>>) {
+    while let Some(chunk) = body.data().await {
+        if chunks.send(chunk).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Reads the chunks of a body as they are received.
+struct ChunkReader {
+    chunk: Bytes,
+    chunks: Receiver<Result<Bytes, hyper::Error>>,
+    error: Option<Error>,
+    limit: Option<usize>,
+    /// The number of bytes read since the last node was sent on.
+    unsent: Rc<Cell<usize>>,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        while self.chunk.is_empty() {
+            match block_on(self.chunks.next()) {
+                Some(Ok(chunk)) => self.chunk = chunk,
+                Some(Err(error)) => {
+                    let io_error = IoError::new(ErrorKind::Other, error.to_string());
+                    self.error = Some(Error::Http(error));
+                    return Err(io_error);
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let length = buf.len().min(self.chunk.len());
+        let unsent = self.unsent.get() + length;
+
+        if let Some(limit) = self.limit {
+            if unsent > limit {
+                let error = Error::ResponseTooLarge { limit };
+                let io_error = IoError::new(ErrorKind::Other, error.to_string());
+                self.error = Some(error);
+                return Err(io_error);
+            }
+        }
+
+        self.unsent.set(unsent);
+        buf[..length].copy_from_slice(&self.chunk.split_to(length));
+
+        Ok(length)
+    }
+}
+
+/// Parses the `KeyValueInfo` of a response, sending on the nodes of its `node`.
+struct ResponseSeed {
+    sender: Sender<Result<Node, Error>>,
+    unsent: Rc<Cell<usize>>,
+}
+
+impl<'de> DeserializeSeed<'de> for ResponseSeed {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ResponseSeed {
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a key-value API response")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "node" {
+                map.next_value_seed(NodeSeed {
+                    sender: self.sender.clone(),
+                    unsent: self.unsent.clone(),
+                })?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a node, sending on the nodes under it and then the node itself.
+struct NodeSeed {
+    sender: Sender<Result<Node, Error>>,
+    unsent: Rc<Cell<usize>>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeSeed {
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a node")
+    }
+
+    fn visit_map<A>(mut self, mut map: A) -> Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut fields = Map::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "nodes" {
+                map.next_value_seed(NodesSeed {
+                    sender: self.sender.clone(),
+                    unsent: self.unsent.clone(),
+                })?;
+            } else {
+                fields.insert(key, map.next_value()?);
+            }
+        }
+
+        let node =
+            serde_json::from_value::<Node>(Value::Object(fields)).map_err(A::Error::custom)?;
+
+        self.unsent.set(0);
+
+        block_on(self.sender.send(Ok(node)))
+            .map_err(|_| A::Error::custom("the stream of nodes was dropped"))
+    }
+}
+
+/// Parses the nodes under a directory.
+struct NodesSeed {
+    sender: Sender<Result<Node, Error>>,
+    unsent: Rc<Cell<usize>>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodesSeed {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodesSeed {
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a list of nodes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while seq
+            .next_element_seed(NodeSeed {
+                sender: self.sender.clone(),
+                unsent: self.unsent.clone(),
+            })?
+            .is_some()
+        {}
+
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use crate::kv::ReadConsistency;
+
 /// Possible conditions for "compare and delete" and "compare and swap" operations.
 #[derive(Debug, Default)]
 pub struct ComparisonConditions<'a> {
@@ -28,8 +30,8 @@ pub struct DeleteOptions<'a> {
 /// Controls the various different ways a get operation can be performed.
 #[derive(Debug, Default)]
 pub struct GetOptions {
-    /// Whether or not to use read linearization to avoid stale data.
-    pub strong_consistency: bool,
+    /// How up to date the data returned must be.
+    pub consistency: ReadConsistency,
     /// Whether or not keys within a directory should be included in the response.
     pub recursive: bool,
     /// Whether or not directory contents will be sorted within the response.
@@ -0,0 +1,193 @@
+//! HTTPS support using [rustls](https://github.com/ctz/rustls) instead of the platform's native
+//! TLS library.
+//!
+//! This module is only available with the `rustls` Cargo feature. Clients using rustls are
+//! constructed with `Client::https_rustls`, `Client::https_rustls_with_config`, or
+//! `ClientBuilder::build_rustls`. Unless a `TlsConfig` sets a CA bundle, servers are verified
+//! against the Mozilla root certificates bundled with the crate, so no certificate store has to be
+//! present on the system.
+
+use std::error::Error as StdError;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::future::{BoxFuture, FutureExt};
+use hyper::client::connect::HttpConnector;
+use hyper::service::Service;
+use hyper::Uri;
+use hyper_rustls::MaybeHttpsStream;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::internal::pemfile;
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, PrivateKey, RootCertStore, ServerCertVerified, ServerCertVerifier,
+    TLSError,
+};
+use tokio_rustls::webpki::{DNSName, DNSNameRef};
+use tokio_rustls::TlsConnector;
+
+use crate::credentials::CredentialsProvider;
+use crate::error::Error;
+use crate::tls::{ReloadingConnector, TlsConfig};
+
+/// A connector for HTTPS clients using rustls.
+#[derive(Clone, Debug)]
+pub struct HttpsConnector {
+    http: HttpConnector,
+    tls: Arc<ReloadingConnector<Rustls>>,
+}
+
+/// A TLS connector and the host name it verifies servers' certificates against, if overridden.
+#[derive(Clone)]
+struct Rustls {
+    connector: TlsConnector,
+    server_name: Option<DNSName>,
+}
+
+impl HttpsConnector {
+    /// Constructs a new `HttpsConnector` that makes TCP connections with the given connector.
+    ///
+    /// If the credentials provider supplies a TLS configuration, it is used instead of the given
+    /// one.
+    pub(crate) fn new(
+        mut http: HttpConnector,
+        config: &TlsConfig,
+        credentials: Option<Arc<dyn CredentialsProvider>>,
+    ) -> Result<Self, Error> {
+        http.enforce_http(false);
+
+        Ok(HttpsConnector {
+            http,
+            tls: Arc::new(ReloadingConnector::new(config, credentials, build)?),
+        })
+    }
+}
+
+impl Service<Uri> for HttpsConnector {
+    type Response = MaybeHttpsStream<TcpStream>;
+    type Error = Box<dyn StdError + Send + Sync>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.http.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let is_https = uri.scheme_str() == Some("https");
+        let tls = self.tls.get();
+        let server_name = match tls.server_name {
+            Some(ref server_name) => Ok(server_name.clone()),
+            None => parse_dns_name(uri.host().unwrap_or_default()),
+        };
+        let connecting = self.http.call(uri);
+
+        async move {
+            let tcp = connecting.await?;
+
+            if !is_https {
+                return Ok(MaybeHttpsStream::Http(tcp));
+            }
+
+            let tls_stream = tls.connector.connect(server_name?.as_ref(), tcp).await?;
+
+            Ok(MaybeHttpsStream::Https(tls_stream))
+        }
+        .boxed()
+    }
+}
+
+/// A certificate verifier that accepts any certificate, for `TlsConfig::insecure_skip_verify`.
+struct NoVerification;
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _: &RootCertStore,
+        _: &[Certificate],
+        _: DNSNameRef<'_>,
+        _: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Builds the TLS connector described by a `TlsConfig`.
+fn build(config: &TlsConfig) -> Result<Rustls, Error> {
+    let server_name = match config.server_name_override() {
+        Some(server_name) => Some(parse_dns_name(server_name)?),
+        None => None,
+    };
+
+    Ok(Rustls {
+        connector: TlsConnector::from(Arc::new(client_config(config)?)),
+        server_name,
+    })
+}
+
+/// Constructs the rustls configuration described by a `TlsConfig`.
+fn client_config(config: &TlsConfig) -> Result<ClientConfig, Error> {
+    let mut client_config = ClientConfig::new();
+    client_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    match config.load_ca()? {
+        Some(ca) => {
+            for certificate in parse_certificates(&ca)? {
+                client_config
+                    .root_store
+                    .add(&certificate)
+                    .map_err(|error| Error::Tls(Box::new(error)))?;
+            }
+        }
+        None => client_config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS),
+    }
+
+    if let Some(identity) = config.load_identity()? {
+        let certificates = parse_certificates(&identity.cert)?;
+        let key = parse_private_key(&identity.key)?;
+
+        client_config.set_single_client_cert(certificates, key)?;
+    }
+
+    if config.skips_verification() {
+        client_config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(NoVerification));
+    }
+
+    Ok(client_config)
+}
+
+/// Parses a host name to verify a server's certificate against.
+fn parse_dns_name(name: &str) -> Result<DNSName, Error> {
+    DNSNameRef::try_from_ascii_str(name)
+        .map(DNSName::from)
+        .map_err(|_| Error::Tls(format!("{} is not a valid DNS name", name).into()))
+}
+
+/// Parses the certificates in a PEM file, which has to contain at least one.
+fn parse_certificates(pem: &[u8]) -> Result<Vec<Certificate>, Error> {
+    match pemfile::certs(&mut BufReader::new(pem)) {
+        Ok(ref certificates) if certificates.is_empty() => {
+            Err(Error::Tls("no certificates found in PEM data".into()))
+        }
+        Ok(certificates) => Ok(certificates),
+        Err(()) => Err(Error::Tls("invalid PEM-encoded certificate".into())),
+    }
+}
+
+/// Parses the first private key in a PEM file, which may be either an RSA or a PKCS #8 key.
+fn parse_private_key(pem: &[u8]) -> Result<PrivateKey, Error> {
+    let invalid = || Error::Tls("invalid PEM-encoded private key".into());
+
+    let mut keys = pemfile::pkcs8_private_keys(&mut BufReader::new(pem)).map_err(|_| invalid())?;
+
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut BufReader::new(pem)).map_err(|_| invalid())?;
+    }
+
+    keys.into_iter()
+        .next()
+        .ok_or_else(|| Error::Tls("no private key found in PEM data".into()))
+}
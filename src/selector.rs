@@ -0,0 +1,229 @@
+//! Strategies for choosing which cluster member receives an API call.
+//!
+//! API calls that only need one cluster member to answer ask the client's `EndpointSelector` for
+//! the order in which to try its endpoints, and move on to the next endpoint whenever one fails.
+//! The selector is told about the outcome of each attempt, so strategies can adapt to the state of
+//! the cluster.
+//!
+//! A selector is chosen with `ClientBuilder::endpoint_selector`. Clients use `InOrder` by default,
+//! or `Weighted` if any of their endpoints was given a weight with `endpoint::Endpoint::weight`.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use hyper::Uri;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+/// A strategy for choosing the order in which cluster members are tried for an API call.
+pub trait EndpointSelector: Debug + Send + Sync {
+    /// Returns the order in which the given endpoints should be tried for a single API call.
+    ///
+    /// Endpoints omitted from the returned list are not tried at all.
+    fn order(&self, endpoints: &[Uri]) -> Vec<Uri>;
+
+    /// Records that an endpoint answered an API call after the given amount of time.
+    fn succeeded(&self, _endpoint: &Uri, _latency: Duration) {}
+
+    /// Records that an endpoint could not answer an API call.
+    fn failed(&self, _endpoint: &Uri) {}
+}
+
+/// Tries endpoints in the order the client was configured with.
+///
+/// The first endpoint receives all API calls as long as it is available.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InOrder;
+
+impl EndpointSelector for InOrder {
+    fn order(&self, endpoints: &[Uri]) -> Vec<Uri> {
+        endpoints.to_vec()
+    }
+}
+
+/// Starts each API call at the endpoint after the one the previous API call started at.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl RoundRobin {
+    /// Constructs a new `RoundRobin`.
+    pub fn new() -> Self {
+        RoundRobin::default()
+    }
+}
+
+impl EndpointSelector for RoundRobin {
+    fn order(&self, endpoints: &[Uri]) -> Vec<Uri> {
+        if endpoints.is_empty() {
+            return vec![];
+        }
+
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % endpoints.len();
+        let mut order = endpoints.to_vec();
+        order.rotate_left(start);
+
+        order
+    }
+}
+
+/// Tries endpoints in a random order for each API call.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Random;
+
+impl EndpointSelector for Random {
+    fn order(&self, endpoints: &[Uri]) -> Vec<Uri> {
+        let mut order = endpoints.to_vec();
+        order.shuffle(&mut thread_rng());
+
+        order
+    }
+}
+
+/// Sends all API calls to one endpoint until it fails, and then sticks to the next one.
+#[derive(Debug, Default)]
+pub struct Sticky {
+    current: Mutex<Option<Uri>>,
+}
+
+impl Sticky {
+    /// Constructs a new `Sticky`.
+    pub fn new() -> Self {
+        Sticky::default()
+    }
+}
+
+impl EndpointSelector for Sticky {
+    fn order(&self, endpoints: &[Uri]) -> Vec<Uri> {
+        let current = self.current.lock().unwrap();
+        let mut order = endpoints.to_vec();
+
+        if let Some(ref current) = *current {
+            if let Some(position) = order.iter().position(|endpoint| endpoint == current) {
+                order.rotate_left(position);
+            }
+        }
+
+        order
+    }
+
+    fn succeeded(&self, endpoint: &Uri, _latency: Duration) {
+        let mut current = self.current.lock().unwrap();
+
+        if current.is_none() {
+            *current = Some(endpoint.clone());
+        }
+    }
+
+    fn failed(&self, endpoint: &Uri) {
+        let mut current = self.current.lock().unwrap();
+
+        if current.as_ref() == Some(endpoint) {
+            *current = None;
+        }
+    }
+}
+
+/// Tries endpoints in order of their observed latency, fastest first.
+///
+/// Endpoints that have not answered an API call yet are tried before all others, so that their
+/// latency gets measured. An endpoint that fails is tried last until it answers again.
+#[derive(Debug, Default)]
+pub struct LeastLatency {
+    latencies: Mutex<HashMap<Uri, Option<Duration>>>,
+}
+
+impl LeastLatency {
+    /// Constructs a new `LeastLatency`.
+    pub fn new() -> Self {
+        LeastLatency::default()
+    }
+}
+
+impl EndpointSelector for LeastLatency {
+    fn order(&self, endpoints: &[Uri]) -> Vec<Uri> {
+        let latencies = self.latencies.lock().unwrap();
+        let mut order = endpoints.to_vec();
+
+        // Unmeasured endpoints sort first, then measured ones by latency, then failed ones.
+        order.sort_by_key(|endpoint| match latencies.get(endpoint) {
+            None => (0, Duration::from_secs(0)),
+            Some(Some(latency)) => (1, *latency),
+            Some(None) => (2, Duration::from_secs(0)),
+        });
+
+        order
+    }
+
+    fn succeeded(&self, endpoint: &Uri, latency: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+
+        // Smooth the measurements so a single slow response doesn't reorder the endpoints.
+        let latency = match latencies.get(endpoint) {
+            Some(Some(average)) => (*average * 3 + latency) / 4,
+            _ => latency,
+        };
+
+        latencies.insert(endpoint.clone(), Some(latency));
+    }
+
+    fn failed(&self, endpoint: &Uri) {
+        self.latencies
+            .lock()
+            .unwrap()
+            .insert(endpoint.clone(), None);
+    }
+}
+
+/// Tries endpoints in a random order for each API call, where endpoints with a higher weight are
+/// more likely to be tried first.
+///
+/// An endpoint with twice the weight of another is tried first twice as often. Endpoints without
+/// a weight have a weight of 1, and endpoints with a weight of 0 are only tried after all others.
+#[derive(Clone, Debug, Default)]
+pub struct Weighted {
+    weights: HashMap<Uri, u32>,
+}
+
+impl Weighted {
+    /// Constructs a new `Weighted` without any weights.
+    pub fn new() -> Self {
+        Weighted::default()
+    }
+
+    /// Sets the weight of an endpoint.
+    pub fn weight(&mut self, endpoint: Uri, weight: u32) -> &mut Self {
+        self.weights.insert(endpoint, weight);
+        self
+    }
+}
+
+impl EndpointSelector for Weighted {
+    fn order(&self, endpoints: &[Uri]) -> Vec<Uri> {
+        let mut rng = thread_rng();
+
+        // Sorting by u^(1/weight) for a uniformly random u gives a weighted random permutation.
+        let mut keyed: Vec<(f64, &Uri)> = endpoints
+            .iter()
+            .map(|endpoint| {
+                let key = match self.weights.get(endpoint).copied().unwrap_or(1) {
+                    0 => -1.0,
+                    weight => rng.gen::<f64>().powf(1.0 / f64::from(weight)),
+                };
+
+                (key, endpoint)
+            })
+            .collect();
+
+        keyed.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+        keyed
+            .into_iter()
+            .map(|(_, endpoint)| endpoint.clone())
+            .collect()
+    }
+}
@@ -4,10 +4,9 @@ use std::collections::HashMap;
 
 use futures::stream::{self, Stream, StreamExt};
 use hyper::client::connect::Connect;
-use hyper::Uri;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::client::{Client, Response};
+use crate::client::{build_url, Client, Response};
 use crate::error::Error;
 
 /// Statistics about an etcd cluster leader.
@@ -160,11 +159,11 @@ pub async fn leader_stats<C>(client: &Client<C>) -> Result<Response<LeaderStats>
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    let uri = build_uri(&client.endpoints()[0], "v2/stats/leader")?;
+    let uri = build_url(&client.cluster().endpoints()[0], "v2/stats/leader")?;
     client.request(uri).await
 }
 
-/// Returns statistics about each cluster member the client was initialized with.
+/// Returns statistics about each of the client's cluster members.
 ///
 /// Fails if JSON decoding fails, which suggests a bug in our schema.
 pub fn self_stats<'a, C>(
@@ -173,16 +172,18 @@ pub fn self_stats<'a, C>(
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    stream::iter(client.endpoints().clone())
+    let endpoints = client.cluster().endpoints().to_vec();
+    let concurrency = endpoints.len();
+
+    stream::iter(endpoints)
         .map(move |endpoint| async move {
-            let uri = build_uri(&endpoint, "v2/stats/self")?;
+            let uri = build_url(&endpoint, "v2/stats/self")?;
             client.request(uri).await
         })
-        .buffer_unordered(client.endpoints().len())
+        .buffer_unordered(concurrency)
 }
 
-/// Returns statistics about operations handled by each etcd member the client was initialized
-/// with.
+/// Returns statistics about operations handled by each of the client's cluster members.
 ///
 /// Fails if JSON decoding fails, which suggests a bug in our schema.
 pub fn store_stats<'a, C>(
@@ -191,15 +192,13 @@ pub fn store_stats<'a, C>(
 where
     C: Clone + Connect + Send + Sync + 'static,
 {
-    stream::iter(client.endpoints().clone())
+    let endpoints = client.cluster().endpoints().to_vec();
+    let concurrency = endpoints.len();
+
+    stream::iter(endpoints)
         .map(move |endpoint| async move {
-            let uri = build_uri(&endpoint, "v2/stats/store")?;
+            let uri = build_url(&endpoint, "v2/stats/store")?;
             client.request(uri).await
         })
-        .buffer_unordered(client.endpoints().len())
-}
-
-/// Constructs the full URL for an API call.
-fn build_uri(endpoint: &Uri, path: &str) -> std::result::Result<Uri, http::uri::InvalidUri> {
-    format!("{}{}", endpoint, path).parse()
+        .buffer_unordered(concurrency)
 }
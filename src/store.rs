@@ -0,0 +1,713 @@
+//! A trait for etcd's key-value API, so that code using it can be given a different backend.
+//!
+//! `KeyValueStore` has the most common API calls of the `kv` module as methods. It is implemented
+//! by `Client`, which makes the API calls to the cluster, and by `MemoryStore`, which keeps the
+//! keys in memory, so code that depends on a `KeyValueStore`, such as through an
+//! `Arc<dyn KeyValueStore>`, can be tested without a running cluster.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use etcd::kv::GetOptions;
+//! use etcd::store::{KeyValueStore, MemoryStore};
+//!
+//! async fn greeting(store: &dyn KeyValueStore) -> String {
+//!     let response = store.get("/greeting", GetOptions::default()).await.unwrap();
+//!     response.data.node.value.unwrap()
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let store: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::new());
+//! store.set("/greeting", "hello", None).await.unwrap();
+//!
+//! assert_eq!(greeting(&*store).await, "hello");
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, FutureExt};
+use hyper::client::connect::Connect;
+use tokio::time::{timeout, timeout_at, Instant};
+
+use crate::client::{Client, ClusterInfo, Response};
+use crate::error::{ApiError, Error, WatchError};
+use crate::kv::{self, Action, GetOptions, KeyPath, KeyValueInfo, Node, WatchOptions};
+
+/// The number of changes a `MemoryStore` keeps for watches of past changes, like etcd.
+const HISTORY: usize = 1000;
+
+/// The key-value API calls of the `kv` module, as an object-safe trait.
+///
+/// Each method behaves like the function of the same name in the `kv` module.
+pub trait KeyValueStore: Send + Sync {
+    /// Gets the value of a node. See `kv::get`.
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+        options: GetOptions,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>>;
+
+    /// Sets the value of a key-value pair. See `kv::set`.
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>>;
+
+    /// Creates a new key-value pair. See `kv::create`.
+    fn create<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>>;
+
+    /// Updates an existing key-value pair. See `kv::update`.
+    fn update<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>>;
+
+    /// Deletes a node. See `kv::delete`.
+    fn delete<'a>(
+        &'a self,
+        key: &'a str,
+        recursive: bool,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>>;
+
+    /// Updates a node only if the given current value and/or current modified index match. See
+    /// `kv::compare_and_swap`.
+    fn compare_and_swap<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+        current_value: Option<&'a str>,
+        current_modified_index: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>>;
+
+    /// Watches a node for changes and returns the new value as soon as a change takes place. See
+    /// `kv::watch`.
+    fn watch<'a>(
+        &'a self,
+        key: &'a str,
+        options: WatchOptions,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, WatchError>>;
+}
+
+impl<C> KeyValueStore for Client<C>
+where
+    C: Clone + Connect + Send + Sync + 'static,
+{
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+        options: GetOptions,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        kv::get(self, key, options).boxed()
+    }
+
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        kv::set(self, key, value, ttl).boxed()
+    }
+
+    fn create<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        kv::create(self, key, value, ttl).boxed()
+    }
+
+    fn update<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        kv::update(self, key, value, ttl).boxed()
+    }
+
+    fn delete<'a>(
+        &'a self,
+        key: &'a str,
+        recursive: bool,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        kv::delete(self, key, recursive).boxed()
+    }
+
+    fn compare_and_swap<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+        current_value: Option<&'a str>,
+        current_modified_index: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        kv::compare_and_swap(self, key, value, ttl, current_value, current_modified_index).boxed()
+    }
+
+    fn watch<'a>(
+        &'a self,
+        key: &'a str,
+        options: WatchOptions,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, WatchError>> {
+        kv::watch(self, key, options).boxed()
+    }
+}
+
+/// A `KeyValueStore` that keeps its keys in memory, for tests.
+///
+/// It behaves like a single etcd member: it validates keys like `kv::KeyPath`, creates missing
+/// parent directories, expires keys after their TTL, fails with the same `ApiError`s as etcd, and
+/// keeps the last 1000 changes for watches. Every read is up to date, whatever its
+/// `ReadConsistency`. Nodes have a `ttl` but no `expiration`. Time is measured with Tokio's clock,
+/// so tests can skip ahead to the expiration of keys with `tokio::time::advance`.
+///
+/// Clones of a store share its keys.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    state: Arc<Mutex<State>>,
+}
+
+impl MemoryStore {
+    /// Constructs a new, empty `MemoryStore`.
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    /// Sets, creates, updates, or compares and swaps a key-value pair.
+    fn write(
+        &self,
+        key: &str,
+        value: &str,
+        ttl: Option<u64>,
+        write: Write<'_>,
+    ) -> Result<Response<KeyValueInfo>, Error> {
+        if let Write::CompareAndSwap(None, None) = write {
+            return Err(Error::InvalidConditions);
+        }
+
+        let key = normalize(key)?;
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.expire(now);
+
+        if key == "/" {
+            return Err(state.error(107, "Root is read only", &key));
+        }
+
+        let existing = state.nodes.get(&key).cloned();
+
+        let action = match (write, &existing) {
+            (Write::Create, Some(_)) => return Err(state.error(105, "Key already exists", &key)),
+            (Write::Update, None) | (Write::CompareAndSwap(..), None) => {
+                return Err(state.error(100, "Key not found", &key))
+            }
+            (_, Some(entry)) if entry.value.is_none() => {
+                return Err(state.error(102, "Not a file", &key))
+            }
+            (Write::CompareAndSwap(current_value, current_modified_index), Some(entry)) => {
+                let mut failed = Vec::new();
+
+                if let Some(current_value) = current_value {
+                    if Some(current_value) != entry.value.as_deref() {
+                        failed.push(format!(
+                            "[{} != {}]",
+                            current_value,
+                            entry.value.as_deref().unwrap_or_default()
+                        ));
+                    }
+                }
+
+                if let Some(current_modified_index) = current_modified_index {
+                    if current_modified_index != entry.modified_index {
+                        failed.push(format!(
+                            "[{} != {}]",
+                            current_modified_index, entry.modified_index
+                        ));
+                    }
+                }
+
+                if !failed.is_empty() {
+                    return Err(state.error(101, "Compare failed", &failed.join(" ")));
+                }
+
+                Action::CompareAndSwap
+            }
+            (Write::Create, None) => Action::Create,
+            (Write::Set, _) => Action::Set,
+            (Write::Update, Some(_)) => Action::Update,
+        };
+
+        state.create_parents(&key)?;
+        state.index += 1;
+
+        let created_index = match (action, &existing) {
+            (Action::Update, Some(entry)) | (Action::CompareAndSwap, Some(entry)) => {
+                entry.created_index
+            }
+            _ => state.index,
+        };
+
+        let entry = Entry {
+            created_index,
+            expires: ttl.map(|ttl| now + Duration::from_secs(ttl)),
+            modified_index: state.index,
+            value: Some(value.to_owned()),
+        };
+        let info = KeyValueInfo {
+            action,
+            node: entry.node(&key, now),
+            prev_node: existing.map(|existing| existing.node(&key, now)),
+        };
+
+        state.nodes.insert(key.clone(), entry);
+        state.record(key, info.clone());
+
+        Ok(state.response(info))
+    }
+}
+
+impl KeyValueStore for MemoryStore {
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+        options: GetOptions,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        let result = normalize(key).and_then(|key| {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            state.expire(now);
+
+            let node = state.tree(&key, options.recursive, now)?;
+
+            Ok(state.response(KeyValueInfo {
+                action: Action::Get,
+                node,
+                prev_node: None,
+            }))
+        });
+
+        futures::future::ready(result).boxed()
+    }
+
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        futures::future::ready(self.write(key, value, ttl, Write::Set)).boxed()
+    }
+
+    fn create<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        futures::future::ready(self.write(key, value, ttl, Write::Create)).boxed()
+    }
+
+    fn update<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        futures::future::ready(self.write(key, value, ttl, Write::Update)).boxed()
+    }
+
+    fn delete<'a>(
+        &'a self,
+        key: &'a str,
+        recursive: bool,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        let result = normalize(key).and_then(|key| {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            state.expire(now);
+
+            if key == "/" {
+                return Err(state.error(107, "Root is read only", &key));
+            }
+
+            let existing = match state.nodes.get(&key) {
+                Some(existing) => existing.clone(),
+                None => return Err(state.error(100, "Key not found", &key)),
+            };
+
+            if existing.value.is_none() && !recursive {
+                return Err(state.error(102, "Not a file", &key));
+            }
+
+            state.index += 1;
+            let info = state.remove(&key, Action::Delete, now);
+
+            Ok(state.response(info))
+        });
+
+        futures::future::ready(result).boxed()
+    }
+
+    fn compare_and_swap<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+        ttl: Option<u64>,
+        current_value: Option<&'a str>,
+        current_modified_index: Option<u64>,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, Error>> {
+        let write = Write::CompareAndSwap(current_value, current_modified_index);
+
+        futures::future::ready(self.write(key, value, ttl, write)).boxed()
+    }
+
+    fn watch<'a>(
+        &'a self,
+        key: &'a str,
+        options: WatchOptions,
+    ) -> BoxFuture<'a, Result<Response<KeyValueInfo>, WatchError>> {
+        let work = async move {
+            let key = normalize(key)?;
+            let mut wait_index = options.index;
+
+            loop {
+                let (changed, next_expiration) = {
+                    let mut state = self.state.lock().unwrap();
+                    state.expire(Instant::now());
+
+                    let wait_index = *wait_index.get_or_insert(state.index + 1);
+
+                    if wait_index <= state.cleared_index {
+                        return Err(state.error(
+                            401,
+                            "The event in requested index is outdated and cleared",
+                            &format!(
+                                "the requested history has been cleared [{}/{}]",
+                                state.cleared_index + 1,
+                                wait_index
+                            ),
+                        ));
+                    }
+
+                    if let Some(info) = state.change(&key, wait_index, options.recursive) {
+                        return Ok(state.response(info));
+                    }
+
+                    // Watches that timed out or were dropped no longer wait for a change.
+                    state.watchers.retain(|watcher| !watcher.is_canceled());
+
+                    let (sender, changed) = oneshot::channel();
+                    state.watchers.push(sender);
+
+                    (changed, state.next_expiration())
+                };
+
+                // Keys only expire while the store is in use, so wake up in time to expire them.
+                match next_expiration {
+                    Some(expiration) => {
+                        timeout_at(expiration, changed).await.ok();
+                    }
+                    None => {
+                        changed.await.ok();
+                    }
+                }
+            }
+        };
+
+        async move {
+            match options.timeout {
+                Some(duration) => match timeout(duration, work).await {
+                    Ok(result) => result.map_err(WatchError::Other),
+                    Err(_) => Err(WatchError::Timeout),
+                },
+                None => work.await.map_err(WatchError::Other),
+            }
+        }
+        .boxed()
+    }
+}
+
+/// The kinds of writes to a key-value pair.
+#[derive(Clone, Copy, Debug)]
+enum Write<'a> {
+    CompareAndSwap(Option<&'a str>, Option<u64>),
+    Create,
+    Set,
+    Update,
+}
+
+/// The keys of a `MemoryStore`, and its history of changes.
+#[derive(Debug, Default)]
+struct State {
+    /// The etcd index of the most recent change that is no longer in `changes`.
+    cleared_index: u64,
+    /// The most recent changes, with the key that was changed.
+    changes: VecDeque<(String, KeyValueInfo)>,
+    /// The etcd index of the most recent change.
+    index: u64,
+    /// The nodes, by key, without the root directory.
+    nodes: BTreeMap<String, Entry>,
+    /// Watches waiting for the next change.
+    watchers: Vec<oneshot::Sender<()>>,
+}
+
+impl State {
+    /// Constructs an `Error::Api` at the current etcd index.
+    fn error(&self, error_code: u64, message: &str, cause: &str) -> Error {
+        Error::Api(ApiError {
+            cause: Some(cause.to_owned()),
+            cluster_id: None,
+            error_code,
+            index: self.index,
+            message: message.to_owned(),
+        })
+    }
+
+    /// Wraps the result of an API call in a response at the current etcd index.
+    fn response(&self, data: KeyValueInfo) -> Response<KeyValueInfo> {
+        Response {
+            cluster_info: ClusterInfo {
+                cluster_id: None,
+                etcd_index: Some(self.index),
+                raft_index: None,
+                raft_term: None,
+            },
+            data,
+        }
+    }
+
+    /// Creates the missing parent directories of a key, at the next etcd index.
+    fn create_parents(&mut self, key: &str) -> Result<(), Error> {
+        let mut parent = String::new();
+        let segments: Vec<_> = key[1..].split('/').collect();
+
+        for segment in &segments[..segments.len() - 1] {
+            parent.push('/');
+            parent.push_str(segment);
+
+            match self.nodes.get(&parent) {
+                Some(entry) if entry.value.is_some() => {
+                    return Err(self.error(104, "Not a directory", &parent))
+                }
+                Some(_) => {}
+                None => {
+                    let entry = Entry {
+                        created_index: self.index + 1,
+                        expires: None,
+                        modified_index: self.index + 1,
+                        value: None,
+                    };
+
+                    self.nodes.insert(parent.clone(), entry);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes a node and everything under it, as a change at the current etcd index.
+    fn remove(&mut self, key: &str, action: Action, now: Instant) -> KeyValueInfo {
+        let entry = self.nodes.remove(key).unwrap();
+        let prefix = format!("{}/", key);
+        let children: Vec<String> = self
+            .nodes
+            .range(prefix.clone()..)
+            .take_while(|(child, _)| child.starts_with(&prefix))
+            .map(|(child, _)| child.clone())
+            .collect();
+
+        for child in children {
+            self.nodes.remove(&child);
+        }
+
+        let mut node = entry.node(key, now);
+        node.modified_index = Some(self.index);
+        node.ttl = None;
+        node.value = None;
+
+        let info = KeyValueInfo {
+            action,
+            node,
+            prev_node: Some(entry.node(key, now)),
+        };
+
+        self.record(key.to_owned(), info.clone());
+
+        info
+    }
+
+    /// Removes the nodes whose TTL has run out.
+    fn expire(&mut self, now: Instant) {
+        let expired: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|(_, entry)| entry.expires.map_or(false, |expires| expires <= now))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in expired {
+            // The node may have gone with an expired directory above it.
+            if self.nodes.contains_key(&key) {
+                self.index += 1;
+                self.remove(&key, Action::Expire, now);
+            }
+        }
+    }
+
+    /// Returns when the next node expires, if any has a TTL.
+    fn next_expiration(&self) -> Option<Instant> {
+        self.nodes.values().filter_map(|entry| entry.expires).min()
+    }
+
+    /// Adds a change to the history and wakes up the waiting watches.
+    fn record(&mut self, key: String, info: KeyValueInfo) {
+        self.changes.push_back((key, info));
+
+        if self.changes.len() > HISTORY {
+            if let Some((_, cleared)) = self.changes.pop_front() {
+                self.cleared_index = cleared.node.modified_index.unwrap_or_default();
+            }
+        }
+
+        for watcher in self.watchers.drain(..) {
+            watcher.send(()).ok();
+        }
+    }
+
+    /// Returns the first change to a key at or after the given etcd index.
+    fn change(&self, key: &str, index: u64, recursive: bool) -> Option<KeyValueInfo> {
+        let under = |parent: &str, child: &str| {
+            parent == "/" || (child.starts_with(parent) && child[parent.len()..].starts_with('/'))
+        };
+
+        self.changes
+            .iter()
+            .filter(|(_, info)| info.node.modified_index.unwrap_or_default() >= index)
+            .find(|(changed, info)| {
+                changed == key
+                    || (recursive && under(key, changed))
+                    || ((info.action == Action::Delete || info.action == Action::Expire)
+                        && under(changed, key))
+            })
+            .map(|(_, info)| info.clone())
+    }
+
+    /// Returns the node for a key, with its children if it is a directory, and their children too
+    /// if `recursive` is true.
+    fn tree(&self, key: &str, recursive: bool, now: Instant) -> Result<Node, Error> {
+        let mut node = if key == "/" {
+            Node {
+                created_index: None,
+                dir: Some(true),
+                expiration: None,
+                key: None,
+                modified_index: None,
+                nodes: None,
+                ttl: None,
+                value: None,
+            }
+        } else {
+            match self.nodes.get(key) {
+                Some(entry) => entry.node(key, now),
+                None => return Err(self.error(100, "Key not found", key)),
+            }
+        };
+
+        if node.dir == Some(true) {
+            let prefix = if key == "/" {
+                "/".to_owned()
+            } else {
+                format!("{}/", key)
+            };
+
+            let mut children = Vec::new();
+
+            for (child, entry) in self.nodes.range(prefix.clone()..) {
+                if !child.starts_with(&prefix) {
+                    break;
+                }
+
+                let name = &child[prefix.len()..];
+
+                if name.contains('/') {
+                    continue;
+                }
+
+                if recursive && entry.value.is_none() {
+                    children.push(self.tree(child, true, now)?);
+                } else {
+                    children.push(entry.node(child, now));
+                }
+            }
+
+            node.nodes = Some(children);
+        }
+
+        Ok(node)
+    }
+}
+
+/// A node of a `MemoryStore`.
+#[derive(Clone, Debug)]
+struct Entry {
+    created_index: u64,
+    expires: Option<Instant>,
+    modified_index: u64,
+    /// The value of a key-value pair, or `None` for a directory.
+    value: Option<String>,
+}
+
+impl Entry {
+    /// Returns the node for this entry, without any children.
+    fn node(&self, key: &str, now: Instant) -> Node {
+        Node {
+            created_index: Some(self.created_index),
+            dir: if self.value.is_none() {
+                Some(true)
+            } else {
+                None
+            },
+            expiration: None,
+            key: Some(key.to_owned()),
+            modified_index: Some(self.modified_index),
+            nodes: None,
+            // Like etcd, report the remaining time rounded up to whole seconds.
+            ttl: self.expires.map(|expires| {
+                let remaining = expires.saturating_duration_since(now);
+
+                remaining.as_secs() as i64 + i64::from(remaining.subsec_nanos() > 0)
+            }),
+            value: self.value.clone(),
+        }
+    }
+}
+
+/// Validates a key and removes any trailing slash.
+fn normalize(key: &str) -> Result<String, Error> {
+    let key = KeyPath::new(key)?;
+
+    match key.as_str() {
+        "/" => Ok("/".to_owned()),
+        key => Ok(key.trim_end_matches('/').to_owned()),
+    }
+}
@@ -0,0 +1,40 @@
+//! Background tasks that keep running for as long as a handle to them is kept.
+
+use std::future::Future;
+use std::time::Duration;
+
+use futures::future::{abortable, AbortHandle};
+use tokio::time::delay_for;
+
+/// A handle to a background task, which stops the task when dropped.
+#[derive(Debug)]
+pub struct TaskHandle {
+    abort_handle: AbortHandle,
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
+
+/// Spawns a task that runs the given closure's future right away and then again each time the
+/// given interval has passed since the previous run finished.
+///
+/// Panics if called outside of a Tokio runtime.
+pub fn spawn_periodic<F, U>(interval: Duration, run: F) -> TaskHandle
+where
+    F: Fn() -> U + Send + 'static,
+    U: Future<Output = ()> + Send,
+{
+    let (task, abort_handle) = abortable(async move {
+        loop {
+            run().await;
+            delay_for(interval).await;
+        }
+    });
+
+    tokio::spawn(task);
+
+    TaskHandle { abort_handle }
+}
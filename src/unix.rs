@@ -0,0 +1,138 @@
+//! Support for cluster members listening on Unix domain sockets.
+//!
+//! Endpoints of the form `unix:///path/to/socket` are accepted by all client constructors and
+//! endpoint setters, and are returned in the same form by `Client::endpoints` and
+//! `health::states`. Since API calls build their URLs by appending to the endpoint, the socket
+//! path is stored hex-encoded in the host part of the URLs the client requests internally.
+//!
+//! Making API calls over a Unix domain socket requires a client using `UnixConnector`, such as
+//! one constructed with `Client::unix` or `ClientBuilder::build_unix`.
+
+use std::ffi::OsStr;
+use std::io::{Error as IoError, ErrorKind};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::future::{BoxFuture, FutureExt};
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use hyper::Uri;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::UnixStream;
+
+/// The URI scheme of endpoints on Unix domain sockets.
+pub(crate) const SCHEME: &str = "unix";
+
+/// A connector for cluster members listening on Unix domain sockets.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnixConnector;
+
+impl UnixConnector {
+    /// Constructs a new `UnixConnector`.
+    pub fn new() -> Self {
+        UnixConnector
+    }
+}
+
+impl Service<Uri> for UnixConnector {
+    type Response = UnixConnection;
+    type Error = IoError;
+    type Future = BoxFuture<'static, Result<UnixConnection, IoError>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        async move {
+            let path = socket_path(&uri).ok_or_else(|| {
+                IoError::new(
+                    ErrorKind::InvalidInput,
+                    format!("{} is not a Unix domain socket endpoint", uri),
+                )
+            })?;
+
+            UnixStream::connect(path).await.map(UnixConnection)
+        }
+        .boxed()
+    }
+}
+
+/// A connection to a cluster member over a Unix domain socket.
+#[derive(Debug)]
+pub struct UnixConnection(UnixStream);
+
+impl AsyncRead for UnixConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for UnixConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+impl Connection for UnixConnection {
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+/// Converts a `unix:///path/to/socket` endpoint into one with the hex-encoded socket path as its
+/// host, or returns `None` if the endpoint is not on a Unix domain socket.
+pub(crate) fn encode_endpoint(endpoint: &str) -> Option<String> {
+    if !endpoint.starts_with("unix://") {
+        return None;
+    }
+
+    let path = &endpoint["unix://".len()..];
+    let host: String = path.bytes().map(|byte| format!("{:02x}", byte)).collect();
+
+    Some(format!("{}://{}/", SCHEME, host))
+}
+
+/// Converts an endpoint converted by `encode_endpoint` back into the `unix:///path/to/socket` form,
+/// or returns `None` if the endpoint is not on a Unix domain socket.
+pub(crate) fn decode_endpoint(uri: &Uri) -> Option<String> {
+    socket_path(uri).map(|path| format!("{}://{}", SCHEME, path.display()))
+}
+
+/// Returns the socket path of an endpoint converted by `encode_endpoint`.
+fn socket_path(uri: &Uri) -> Option<PathBuf> {
+    if uri.scheme_str() != Some(SCHEME) {
+        return None;
+    }
+
+    let host = uri.host()?;
+
+    if host.len() % 2 != 0 {
+        return None;
+    }
+
+    let bytes = (0..host.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(host.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some(PathBuf::from(OsStr::from_bytes(&bytes)))
+}
@@ -1,5 +1,13 @@
+#![allow(clippy::bool_assert_comparison)]
+
+use std::time::Duration;
+
 use etcd::auth::{self, AuthChange, NewUser, Role, RoleUpdate, UserUpdate};
-use etcd::{BasicAuth, Client};
+use etcd::{BasicAuth, Client, ClientBuilder};
+
+use crate::test::fake_member;
+
+mod test;
 
 #[tokio::test]
 async fn auth() {
@@ -71,3 +79,23 @@ async fn auth() {
     let response = auth::disable(&authed_client).await.unwrap();
     assert_eq!(response.data, AuthChange::Changed);
 }
+
+#[tokio::test]
+async fn delete_is_verified_after_timeout() {
+    let client = Client::new(&["http://etcd:2379"], None).unwrap();
+    auth::create_role(&client, Role::new("verified"))
+        .await
+        .unwrap();
+
+    // The role is deleted as if by an earlier attempt whose response was lost.
+    auth::delete_role(&client, "verified").await.unwrap();
+
+    // A member whose answers arrive too late, so attempts made to it time out.
+    let (endpoint, _requests) = fake_member(Duration::from_secs(1), |_| ("200 OK", "{}"));
+    let slow_client = ClientBuilder::new(&[&endpoint, "http://etcd:2379"])
+        .request_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    auth::delete_role(&slow_client, "verified").await.unwrap();
+}
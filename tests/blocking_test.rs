@@ -0,0 +1,114 @@
+#![cfg(feature = "blocking")]
+
+use std::thread;
+use std::time::Duration;
+
+use etcd::blocking::{self, Client};
+use etcd::kv::{Action, GetOptions, WatchError, WatchOptions};
+use etcd::Error;
+use hyper::client::connect::HttpConnector;
+
+fn client() -> Client<HttpConnector> {
+    let client = etcd::Client::new(&["http://etcd:2379"], None).unwrap();
+    Client::new(client).unwrap()
+}
+
+#[test]
+fn kv() {
+    let client = client();
+
+    let response = blocking::kv::set(&client, "/test/blocking", "bar", None).unwrap();
+    assert_eq!(response.data.action, Action::Set);
+
+    let response = blocking::kv::get(&client, "/test/blocking", GetOptions::default()).unwrap();
+    assert_eq!(response.data.node.value.unwrap(), "bar");
+
+    match blocking::kv::create(&client, "/test/blocking", "baz", None) {
+        Err(Error::Api(ref error)) => assert_eq!(error.message, "Key already exists"),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+
+    blocking::kv::delete(&client, "/test/blocking", false).unwrap();
+}
+
+#[test]
+fn watch() {
+    let client = client();
+    let response = blocking::kv::set(&client, "/test/watched", "foo", None).unwrap();
+    let index = response.data.node.modified_index.unwrap();
+
+    let writer = client.clone();
+    let child = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        blocking::kv::set(&writer, "/test/watched", "bar", None).unwrap();
+    });
+
+    // Watching from the next index finds the change even if it is made before the watch starts.
+    let options = WatchOptions {
+        index: Some(index + 1),
+        ..Default::default()
+    };
+    let response = blocking::kv::watch(&client, "/test/watched", options).unwrap();
+    assert_eq!(response.data.node.value.unwrap(), "bar");
+
+    child.join().unwrap();
+
+    let options = WatchOptions {
+        timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+
+    match blocking::kv::watch(&client, "/test/watched", options) {
+        Err(WatchError::Timeout) => {}
+        result => panic!("expected a timeout, got {:?}", result),
+    }
+}
+
+#[test]
+fn calls_from_several_threads() {
+    let client = client();
+
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let client = client.clone();
+
+            thread::spawn(move || {
+                let key = format!("/test/thread{}", i);
+                blocking::kv::set(&client, &key, "bar", None).unwrap();
+                blocking::kv::get(&client, &key, GetOptions::default()).unwrap()
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap().data.node.value.unwrap(), "bar");
+    }
+}
+
+#[test]
+fn health_and_versions() {
+    let client = client();
+
+    assert!(client.health().into_iter().all(|health| health.is_ok()));
+    assert!(client.versions().into_iter().all(|version| version.is_ok()));
+}
+
+#[test]
+fn members_and_stats() {
+    let client = client();
+
+    assert!(!blocking::members::list(&client).unwrap().data.is_empty());
+    blocking::stats::leader_stats(&client).unwrap();
+
+    for stats in blocking::stats::self_stats(&client) {
+        stats.unwrap();
+    }
+}
+
+#[tokio::test]
+#[should_panic(expected = "Cannot start a runtime from within a runtime")]
+async fn panics_in_async_context() {
+    let client = client();
+
+    blocking::kv::get(&client, "/test/foo", GetOptions::default()).ok();
+}
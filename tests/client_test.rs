@@ -1,6 +1,14 @@
+use std::net::TcpListener;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use etcd::kv::{self, GetOptions};
+use etcd::{members, Client, ClientBuilder, Error};
+use futures::future::join_all;
 use futures::stream::StreamExt;
+use hyper::header::{HeaderName, HeaderValue};
 
-use crate::test::TestClient;
+use crate::test::{fake_member, header, recording_member, stalled_member, TestClient};
 
 mod test;
 
@@ -26,3 +34,393 @@ async fn versions() {
         assert_eq!(response.data.server_version, "2.3.8");
     }
 }
+
+#[tokio::test]
+async fn builder() {
+    let client = ClientBuilder::new(&["http://etcd:2379"])
+        .request_timeout(Duration::from_secs(5))
+        .connect_timeout(Duration::from_secs(1))
+        .tcp_keepalive(Duration::from_secs(30))
+        .pool_idle_timeout(Duration::from_secs(30))
+        .max_retries(1)
+        .build()
+        .unwrap();
+
+    let mut health = client.health();
+
+    while let Some(response) = health.next().await {
+        assert_eq!(response.unwrap().data.health, "true");
+    }
+}
+
+#[tokio::test]
+async fn request_timeout() {
+    // A listener that never accepts connections, so requests to it never receive a response.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+    let client = ClientBuilder::new(&[&endpoint])
+        .request_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    let error = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .expect_err("expected a timeout");
+
+    match error {
+        Error::ClusterUnavailable(ref errors) => match errors[..] {
+            [Error::Timeout] => {}
+            _ => panic!("expected a timeout, got {:?}", errors),
+        },
+        error => panic!("expected a timeout, got {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn request_timeout_while_reading_body() {
+    let client = ClientBuilder::new(&[&stalled_member()])
+        .request_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    let error = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .expect_err("expected a timeout");
+
+    match error {
+        Error::ClusterUnavailable(ref errors) => match errors[..] {
+            [Error::Timeout] => {}
+            _ => panic!("expected a timeout, got {:?}", errors),
+        },
+        error => panic!("expected a timeout, got {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn retry_backoff() {
+    let (endpoint, requests) = fake_member(Duration::from_secs(0), |_| {
+        (
+            "500 Internal Server Error",
+            r#"{"errorCode":300,"message":"Raft Internal Error","index":1}"#,
+        )
+    });
+    let client = ClientBuilder::new(&[&endpoint])
+        .max_retries(2)
+        .retry_backoff(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+    kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap_err();
+
+    // The retries wait 100 and then 200 milliseconds.
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(requests.try_iter().count(), 3);
+}
+
+#[tokio::test]
+async fn cluster_unavailable() {
+    // Listeners that are closed right away, so connections to them are refused.
+    let endpoints: Vec<String> = (0..2)
+        .map(|_| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/", listener.local_addr().unwrap())
+        })
+        .collect();
+    let endpoints: Vec<&str> = endpoints.iter().map(String::as_str).collect();
+
+    let client = ClientBuilder::new(&endpoints).build().unwrap();
+
+    let error = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .expect_err("expected the cluster to be unavailable");
+
+    match error {
+        Error::ClusterUnavailable(ref errors) => {
+            assert_eq!(errors.len(), 2);
+
+            for error in errors {
+                match error {
+                    Error::Http(_) => {}
+                    error => panic!("expected an HTTP error, got {:?}", error),
+                }
+            }
+        }
+        error => panic!("expected the cluster to be unavailable, got {:?}", error),
+    }
+}
+
+#[test]
+fn set_endpoints() {
+    let client = Client::new(&["http://etcd1:2379"], None).unwrap();
+    let clone = client.clone();
+
+    client
+        .set_endpoints(&["http://etcd2:2379", "http://etcd3:2379"])
+        .unwrap();
+
+    assert_eq!(
+        clone.endpoints(),
+        vec!["http://etcd2:2379/", "http://etcd3:2379/",]
+    );
+
+    match client.set_endpoints(&[]) {
+        Err(Error::NoEndpoints) => {}
+        result => panic!("expected Error::NoEndpoints, got {:?}", result),
+    }
+
+    assert_eq!(clone.endpoints().len(), 2);
+}
+
+#[test]
+fn add_and_remove_endpoints() {
+    let client = Client::new(&["http://etcd1:2379"], None).unwrap();
+    let clone = client.clone();
+
+    client.add_endpoint("http://etcd2:2379").unwrap();
+    client.add_endpoint("http://etcd2:2379").unwrap();
+
+    assert_eq!(
+        clone.endpoints(),
+        vec!["http://etcd1:2379/", "http://etcd2:2379/",]
+    );
+
+    client.remove_endpoint("http://etcd1:2379").unwrap();
+
+    assert_eq!(clone.endpoints(), vec!["http://etcd2:2379/"]);
+
+    match client.remove_endpoint("http://etcd2:2379") {
+        Err(Error::NoEndpoints) => {}
+        result => panic!("expected Error::NoEndpoints, got {:?}", result),
+    }
+
+    assert_eq!(clone.endpoints().len(), 1);
+}
+
+#[tokio::test]
+async fn cluster_id_is_learned() {
+    let client = Client::new(&["http://etcd:2379"], None).unwrap();
+
+    assert_eq!(client.cluster_id(), None);
+
+    let response = members::list(&client).await.unwrap();
+
+    assert!(response.cluster_info.cluster_id.is_some());
+    assert_eq!(client.cluster_id(), response.cluster_info.cluster_id);
+}
+
+#[tokio::test]
+async fn cluster_id_mismatch() {
+    let client = ClientBuilder::new(&["http://etcd:2379"])
+        .cluster_id("0000000000000000")
+        .build()
+        .unwrap();
+
+    match members::list(&client).await {
+        Err(Error::ClusterIdMismatch { expected, .. }) => assert_eq!(expected, "0000000000000000"),
+        result => panic!("expected Error::ClusterIdMismatch, got {:?}", result),
+    }
+
+    // Logical errors from a member of another cluster are rejected too.
+    match kv::get(&client, "/test/missing", GetOptions::default()).await {
+        Err(Error::ClusterIdMismatch { expected, .. }) => assert_eq!(expected, "0000000000000000"),
+        result => panic!("expected Error::ClusterIdMismatch, got {:?}", result),
+    }
+
+    let mut versions = client.versions();
+
+    while let Some(response) = versions.next().await {
+        match response {
+            Err(Error::ClusterIdMismatch { .. }) => {}
+            result => panic!("expected Error::ClusterIdMismatch, got {:?}", result),
+        }
+    }
+}
+
+#[tokio::test]
+async fn default_user_agent() {
+    let (endpoint, requests) = recording_member();
+    let client = ClientBuilder::new(&[&endpoint]).build().unwrap();
+
+    client.health().next().await.unwrap().unwrap();
+
+    let user_agent = header(&requests.recv().unwrap(), "user-agent").unwrap();
+    assert!(user_agent.starts_with("etcd/"));
+}
+
+#[tokio::test]
+async fn default_headers() {
+    let (endpoint, requests) = recording_member();
+    let tenant = HeaderName::from_static("x-tenant");
+
+    let client = ClientBuilder::new(&[&endpoint])
+        .user_agent(HeaderValue::from_static("billing-service/1.0"))
+        .default_header(tenant.clone(), HeaderValue::from_static("billing"))
+        .build()
+        .unwrap();
+
+    client.health().next().await.unwrap().unwrap();
+
+    let request = requests.recv().unwrap();
+    assert_eq!(
+        header(&request, "user-agent").unwrap(),
+        "billing-service/1.0"
+    );
+    assert_eq!(header(&request, "x-tenant").unwrap(), "billing");
+
+    // A header set on a copy of the client replaces the default one only for the copy.
+    let other_tenant = client.with_header(tenant, HeaderValue::from_static("search"));
+
+    other_tenant.health().next().await.unwrap().unwrap();
+    assert_eq!(
+        header(&requests.recv().unwrap(), "x-tenant").unwrap(),
+        "search"
+    );
+
+    client.health().next().await.unwrap().unwrap();
+    assert_eq!(
+        header(&requests.recv().unwrap(), "x-tenant").unwrap(),
+        "billing"
+    );
+}
+
+#[tokio::test]
+async fn max_response_size() {
+    let value = "x".repeat(2000);
+    let client = TestClient::no_destructor();
+
+    kv::set(&client, "/test/max_response_size", &value, Some(60))
+        .await
+        .unwrap();
+
+    let client = ClientBuilder::new(&["http://etcd:2379"])
+        .max_response_size(1000)
+        .build()
+        .unwrap();
+
+    match kv::get(&client, "/test/max_response_size", GetOptions::default()).await {
+        Err(Error::ResponseTooLarge { limit }) => assert_eq!(limit, 1000),
+        result => panic!("expected Error::ResponseTooLarge, got {:?}", result),
+    }
+
+    // Streamed responses are limited per node.
+    let nodes: Vec<_> = kv::get_nodes(&client, "/test/max_response_size", GetOptions::default())
+        .collect()
+        .await;
+    assert_eq!(nodes.len(), 1);
+    match nodes[0] {
+        Err(Error::ResponseTooLarge { limit }) => assert_eq!(limit, 1000),
+        ref result => panic!("expected Error::ResponseTooLarge, got {:?}", result),
+    }
+
+    let client = ClientBuilder::new(&["http://etcd:2379"])
+        .max_response_size(10_000)
+        .build()
+        .unwrap();
+
+    let response = kv::get(&client, "/test/max_response_size", GetOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(response.data.node.value.unwrap(), value);
+}
+
+#[tokio::test]
+async fn max_response_size_of_streamed_nodes() {
+    let value = "x".repeat(500);
+    let client = TestClient::no_destructor();
+
+    for i in 0..10 {
+        let key = format!("/test/max_response_size_of_streamed_nodes/{}", i);
+        kv::set(&client, &key, &value, Some(60)).await.unwrap();
+    }
+
+    let client = ClientBuilder::new(&["http://etcd:2379"])
+        .max_response_size(1000)
+        .build()
+        .unwrap();
+
+    // The response is larger than the limit, but none of its nodes is.
+    let nodes: Vec<_> = kv::get_nodes(
+        &client,
+        "/test/max_response_size_of_streamed_nodes",
+        GetOptions::default(),
+    )
+    .collect()
+    .await;
+    assert_eq!(nodes.len(), 11);
+    assert!(nodes.iter().all(Result::is_ok));
+}
+
+#[tokio::test]
+async fn coalesce_reads() {
+    let (endpoint, requests) = slow_member();
+    let client = ClientBuilder::new(&[&endpoint])
+        .coalesce_reads(true)
+        .build()
+        .unwrap();
+
+    let results = join_all((0..10).map(|_| kv::get(&client, "/foo", GetOptions::default()))).await;
+
+    for result in results {
+        assert_eq!(result.unwrap().data.node.value.unwrap(), "bar");
+    }
+    assert_eq!(requests.try_iter().count(), 1);
+
+    // Reads with other options, or that start after the read in flight is answered, are not
+    // coalesced with it.
+    let recursive = GetOptions {
+        recursive: true,
+        ..Default::default()
+    };
+    let (first, second) = futures::join!(
+        kv::get(&client, "/foo", GetOptions::default()),
+        kv::get(&client, "/foo", recursive),
+    );
+    first.unwrap();
+    second.unwrap();
+    assert_eq!(requests.try_iter().count(), 2);
+
+    let results =
+        join_all((0..10).map(|_| kv::get(&client, "/missing", GetOptions::default()))).await;
+
+    for result in results {
+        match result {
+            Err(Error::Api(ref error)) => assert_eq!(error.error_code, 100),
+            result => panic!("expected Error::Api, got {:?}", result),
+        }
+    }
+    assert_eq!(requests.try_iter().count(), 1);
+}
+
+#[tokio::test]
+async fn reads_not_coalesced_by_default() {
+    let (endpoint, requests) = slow_member();
+    let client = ClientBuilder::new(&[&endpoint]).build().unwrap();
+
+    let results = join_all((0..3).map(|_| kv::get(&client, "/foo", GetOptions::default()))).await;
+
+    for result in results {
+        result.unwrap();
+    }
+    assert_eq!(requests.try_iter().count(), 3);
+}
+
+/// Starts a fake cluster member that answers gets of `/foo` after a delay.
+fn slow_member() -> (String, Receiver<String>) {
+    fake_member(Duration::from_millis(200), |request| {
+        if request.starts_with("GET /v2/keys/foo") {
+            (
+                "200 OK",
+                r#"{"action":"get","node":{"key":"/foo","value":"bar","modifiedIndex":1,"createdIndex":1}}"#,
+            )
+        } else {
+            (
+                "404 Not Found",
+                r#"{"errorCode":100,"message":"Key not found","cause":"/missing","index":1}"#,
+            )
+        }
+    })
+}
@@ -0,0 +1,63 @@
+use etcd::connector::BoxConnector;
+use etcd::kv::{self, GetOptions};
+use etcd::{ClientBuilder, DynClient};
+use hyper::client::connect::HttpConnector;
+
+#[cfg(feature = "tls")]
+use crate::test::TestClient;
+
+mod test;
+
+/// A struct that holds a client without naming its connector type.
+struct Store {
+    client: DynClient,
+}
+
+impl Store {
+    async fn get(&self, key: &str) -> String {
+        let response = kv::get(&self.client, key, GetOptions::default())
+            .await
+            .unwrap();
+
+        response.data.node.value.unwrap()
+    }
+}
+
+#[tokio::test]
+async fn into_dyn() {
+    let client = ClientBuilder::new(&["http://etcd:2379"]).build().unwrap();
+    kv::set(&client, "/test/into_dyn", "bar", Some(60))
+        .await
+        .unwrap();
+
+    let store = Store {
+        client: client.into_dyn(),
+    };
+
+    assert_eq!(store.get("/test/into_dyn").await, "bar");
+    assert_eq!(store.client.endpoints().len(), 1);
+}
+
+#[tokio::test]
+async fn build_with_box_connector() {
+    let client: DynClient = ClientBuilder::new(&["http://etcd:2379"])
+        .build_with_connector(BoxConnector::new(HttpConnector::new()))
+        .unwrap();
+
+    kv::set(&client, "/test/box_connector", "bar", Some(60))
+        .await
+        .unwrap();
+
+    let store = Store { client };
+    assert_eq!(store.get("/test/box_connector").await, "bar");
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn https_into_dyn() {
+    let client: DynClient = TestClient::https(true).clone().into_dyn();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
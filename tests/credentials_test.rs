@@ -0,0 +1,275 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+#[cfg(feature = "tls")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "tls")]
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use etcd::credentials::{CredentialFiles, CredentialsProvider};
+#[cfg(feature = "tls")]
+use etcd::kv;
+#[cfg(feature = "tls")]
+use etcd::tls::TlsConfig;
+use etcd::{BasicAuth, ClientBuilder, Error};
+use futures::StreamExt;
+#[cfg(feature = "tls")]
+use log::{LevelFilter, Log, Metadata, Record};
+
+#[cfg(feature = "tls")]
+use crate::test::tls_config;
+use crate::test::{header, recording_member};
+
+mod test;
+
+#[test]
+fn reload() {
+    let password_file = temp_file("reload", "first\n");
+    let credentials = CredentialFiles::new()
+        .basic_auth("root", &password_file)
+        .load()
+        .unwrap();
+
+    assert_eq!(credentials.basic_auth(), Some(basic_auth("first")));
+    assert!(!credentials.reload().unwrap());
+
+    write_later(&password_file, "second\n");
+
+    assert!(credentials.reload().unwrap());
+    assert_eq!(credentials.basic_auth(), Some(basic_auth("second")));
+}
+
+#[test]
+fn reload_failure_keeps_credentials() {
+    let password_file = temp_file("reload_failure", "first");
+    let credentials = CredentialFiles::new()
+        .basic_auth("root", &password_file)
+        .load()
+        .unwrap();
+
+    fs::remove_file(&password_file).unwrap();
+
+    match credentials.reload() {
+        Err(Error::Credentials(_)) => {}
+        result => panic!("expected a credentials error, got {:?}", result),
+    }
+
+    assert_eq!(credentials.basic_auth(), Some(basic_auth("first")));
+}
+
+#[test]
+fn missing_password_file() {
+    let result = CredentialFiles::new()
+        .basic_auth("root", "/nonexistent/password")
+        .load();
+
+    match result {
+        Err(Error::Credentials(_)) => {}
+        result => panic!("expected a credentials error, got {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn requests_use_current_credentials() {
+    let password_file = temp_file("requests", "first");
+    let credentials = Arc::new(
+        CredentialFiles::new()
+            .basic_auth("root", &password_file)
+            .load()
+            .unwrap(),
+    );
+    let (endpoint, requests) = recording_member();
+
+    let client = ClientBuilder::new(&[&endpoint])
+        .credentials_provider(credentials.clone())
+        .build()
+        .unwrap();
+
+    client.health().next().await.unwrap().unwrap();
+    assert_eq!(
+        header(&requests.recv().unwrap(), "authorization").unwrap(),
+        "Basic cm9vdDpmaXJzdA=="
+    );
+
+    write_later(&password_file, "second");
+    credentials.reload().unwrap();
+
+    client.health().next().await.unwrap().unwrap();
+    assert_eq!(
+        header(&requests.recv().unwrap(), "authorization").unwrap(),
+        "Basic cm9vdDpzZWNvbmQ="
+    );
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn reload_tls_config() {
+    let ca_file = temp_file("reload_tls_config", "first");
+    let mut tls_config = TlsConfig::new();
+    tls_config.ca_file(&ca_file);
+    let credentials = CredentialFiles::new()
+        .tls_config(tls_config)
+        .load()
+        .unwrap();
+
+    let first = credentials.tls_config().unwrap();
+    assert!(!credentials.reload().unwrap());
+    assert!(Arc::ptr_eq(&first, &credentials.tls_config().unwrap()));
+
+    write_later(&ca_file, "second");
+
+    assert!(credentials.reload().unwrap());
+    assert!(!Arc::ptr_eq(&first, &credentials.tls_config().unwrap()));
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn new_connections_use_current_tls_config() {
+    let credentials = Arc::new(SwitchableTls(Mutex::new(Arc::new(tls_config(false)))));
+    let client = ClientBuilder::new(&["https://etcdsecure:2379"])
+        .credentials_provider(credentials.clone())
+        .build_https_with_config(&TlsConfig::new())
+        .unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap_err();
+
+    *credentials.0.lock().unwrap() = Arc::new(tls_config(true));
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn invalid_tls_config_is_retried() {
+    let credentials = Arc::new(SwitchableTls(Mutex::new(Arc::new(tls_config(false)))));
+    let client = ClientBuilder::new(&["https://etcdsecure:2379"])
+        .credentials_provider(credentials.clone())
+        .build_https_with_config(&TlsConfig::new())
+        .unwrap();
+
+    let cert_file = temp_file("invalid_tls_config_is_retried", "");
+    fs::remove_file(&cert_file).unwrap();
+    let mut config = tls_config(false);
+    config
+        .cert_file(&cert_file)
+        .key_file("/source/tests/ssl/client-key.pem");
+    *credentials.0.lock().unwrap() = Arc::new(config);
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap_err();
+
+    fs::copy("/source/tests/ssl/client.pem", &cert_file).unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn invalid_tls_config_is_logged_once() {
+    log::set_logger(&INVALID_TLS_CONFIG_ERRORS).unwrap();
+    log::set_max_level(LevelFilter::Error);
+
+    let credentials = Arc::new(SwitchableTls(Mutex::new(Arc::new(tls_config(false)))));
+    let client = ClientBuilder::new(&["https://etcdsecure:2379"])
+        .credentials_provider(credentials.clone())
+        .build_https_with_config(&TlsConfig::new())
+        .unwrap();
+
+    let cert_file = temp_file("invalid_tls_config_is_logged_once", "");
+    fs::remove_file(&cert_file).unwrap();
+    let mut config = tls_config(false);
+    config
+        .cert_file(&cert_file)
+        .key_file("/source/tests/ssl/client-key.pem");
+    *credentials.0.lock().unwrap() = Arc::new(config);
+
+    for _ in 0..3 {
+        kv::set(&client, "/test/foo", "bar", Some(60))
+            .await
+            .unwrap_err();
+    }
+
+    assert_eq!(INVALID_TLS_CONFIG_ERRORS.0.load(Ordering::SeqCst), 1);
+}
+
+/// Counts the errors logged about the invalid TLS configuration of
+/// `invalid_tls_config_is_logged_once`.
+#[cfg(feature = "tls")]
+static INVALID_TLS_CONFIG_ERRORS: InvalidTlsConfigErrors =
+    InvalidTlsConfigErrors(AtomicUsize::new(0));
+
+#[cfg(feature = "tls")]
+struct InvalidTlsConfigErrors(AtomicUsize);
+
+#[cfg(feature = "tls")]
+impl Log for InvalidTlsConfigErrors {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if record
+            .args()
+            .to_string()
+            .contains("invalid_tls_config_is_logged_once")
+        {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// A credentials provider whose TLS configuration can be replaced.
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+struct SwitchableTls(Mutex<Arc<TlsConfig>>);
+
+#[cfg(feature = "tls")]
+impl CredentialsProvider for SwitchableTls {
+    fn basic_auth(&self) -> Option<BasicAuth> {
+        None
+    }
+
+    fn tls_config(&self) -> Option<Arc<TlsConfig>> {
+        Some(self.0.lock().unwrap().clone())
+    }
+}
+
+fn basic_auth(password: &str) -> BasicAuth {
+    BasicAuth {
+        username: "root".to_string(),
+        password: password.to_string(),
+    }
+}
+
+/// Creates a file with the given contents in a fresh temporary directory.
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!(
+        "etcd-credentials-test-{}-{}",
+        std::process::id(),
+        name
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("file");
+    fs::write(&path, contents).unwrap();
+
+    path
+}
+
+/// Replaces the contents of a file, waiting first so that its modification time changes.
+fn write_later(path: &PathBuf, contents: &str) {
+    thread::sleep(Duration::from_millis(50));
+    fs::write(path, contents).unwrap();
+}
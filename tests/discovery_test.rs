@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use etcd::discovery::{self, SrvRecord, StaticResolver};
+#[cfg(feature = "tls")]
+use etcd::ClientBuilder;
+use etcd::{members, Client, Error};
+use hyper::client::HttpConnector;
+use hyper::Uri;
+use tokio::time::delay_for;
+
+#[tokio::test]
+async fn sync() {
+    let client = Client::new(&["http://unreachable:2379", "http://etcd:2379"], None).unwrap();
+
+    let client_urls = client_urls(&client).await;
+    let endpoints = discovery::sync(&client).await.unwrap().data;
+
+    assert_eq!(endpoints, client_urls);
+    assert_eq!(client.endpoints(), client_urls);
+}
+
+#[tokio::test]
+async fn spawn_sync() {
+    let client = Client::new(&["http://unreachable:2379", "http://etcd:2379"], None).unwrap();
+    let client_urls = client_urls(&client).await;
+
+    let _handle = discovery::spawn_sync(&client, Duration::from_secs(60));
+
+    for _ in 0..50 {
+        if client.endpoints() == client_urls {
+            break;
+        }
+
+        delay_for(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(client.endpoints(), client_urls);
+}
+
+/// Returns the client URLs of all cluster members.
+async fn client_urls(client: &Client<HttpConnector>) -> Vec<String> {
+    let members = members::list(client).await.unwrap().data;
+
+    members
+        .iter()
+        .flat_map(|member| &member.client_urls)
+        .map(|url| url.parse::<Uri>().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn srv_endpoints() {
+    let resolver = resolver();
+    let endpoints = discovery::srv_endpoints(&resolver, "example.com")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        endpoints,
+        vec![
+            "https://etcd1.example.com:2379",
+            "http://etcd2.example.com:2379",
+            "http://etcd3.example.com:4001",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn srv_endpoints_without_records() {
+    let resolver = StaticResolver::new();
+
+    match discovery::srv_endpoints(&resolver, "example.com").await {
+        Err(Error::NoEndpoints) => {}
+        result => panic!("expected Error::NoEndpoints, got {:?}", result),
+    }
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn builder_from_srv() {
+    let resolver = resolver();
+    let client = ClientBuilder::from_srv(&resolver, "example.com")
+        .await
+        .unwrap()
+        .build_https()
+        .unwrap();
+
+    assert_eq!(client.endpoints(), endpoints());
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn client_from_srv() {
+    let resolver = resolver();
+    let client = Client::from_srv(&resolver, "example.com", None)
+        .await
+        .unwrap();
+
+    assert_eq!(client.endpoints(), endpoints());
+}
+
+#[tokio::test]
+async fn sync_srv() {
+    let client = Client::new(&["http://etcd:2379"], None).unwrap();
+    let resolver = resolver();
+
+    let endpoints = discovery::sync_srv(&client, &resolver, "example.com")
+        .await
+        .unwrap();
+
+    assert_eq!(endpoints, self::endpoints());
+    assert_eq!(client.endpoints(), self::endpoints());
+}
+
+#[tokio::test]
+async fn spawn_srv_sync() {
+    let client = Client::new(&["http://etcd:2379"], None).unwrap();
+    let _handle =
+        discovery::spawn_srv_sync(&client, resolver(), "example.com", Duration::from_secs(60));
+
+    for _ in 0..50 {
+        if client.endpoints() == endpoints() {
+            break;
+        }
+
+        delay_for(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(client.endpoints(), endpoints());
+}
+
+/// Returns a resolver with SRV records for a cluster under example.com.
+fn resolver() -> StaticResolver {
+    let mut resolver = StaticResolver::new();
+
+    resolver
+        .insert(
+            "_etcd-client-ssl._tcp.example.com",
+            vec![SrvRecord {
+                target: "etcd1.example.com.".to_string(),
+                port: 2379,
+            }],
+        )
+        .insert(
+            "_etcd-client._tcp.example.com",
+            vec![
+                SrvRecord {
+                    target: "etcd2.example.com.".to_string(),
+                    port: 2379,
+                },
+                SrvRecord {
+                    target: "etcd3.example.com.".to_string(),
+                    port: 4001,
+                },
+            ],
+        );
+
+    resolver
+}
+
+/// Returns the endpoints of the cluster described by `resolver`.
+fn endpoints() -> Vec<String> {
+    vec![
+        "https://etcd1.example.com:2379/".to_string(),
+        "http://etcd2.example.com:2379/".to_string(),
+        "http://etcd3.example.com:4001/".to_string(),
+    ]
+}
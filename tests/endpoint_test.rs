@@ -0,0 +1,177 @@
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+
+#[cfg(feature = "tls")]
+use etcd::credentials::CredentialFiles;
+use etcd::endpoint::Endpoint;
+use etcd::kv::{self, GetOptions};
+#[cfg(feature = "tls")]
+use etcd::tls::TlsConfig;
+use etcd::{members, stats};
+use etcd::{BasicAuth, ClientBuilder, Error};
+use futures::StreamExt;
+
+#[cfg(feature = "tls")]
+use crate::test::tls_config;
+use crate::test::{header, recording_member, request_path};
+
+mod test;
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn mixed_http_and_https() {
+    let mut https = Endpoint::new("https://etcdsecure:2379");
+    https.tls_config(tls_config(true));
+
+    let client = ClientBuilder::from_endpoints(&[https, Endpoint::new("http://etcd:2379")])
+        .build_per_endpoint()
+        .unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+
+    client.remove_endpoint("https://etcdsecure:2379").unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn endpoint_tls_config_with_client_wide_tls_credentials() {
+    let credentials = CredentialFiles::new()
+        .tls_config(tls_config(false))
+        .load()
+        .unwrap();
+
+    let mut https = Endpoint::new("https://etcdsecure:2379");
+    https.tls_config(tls_config(true));
+
+    let client = ClientBuilder::from_endpoints(&[https])
+        .credentials_provider(Arc::new(credentials))
+        .build_per_endpoint()
+        .unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn invalid_tls_config() {
+    let mut tls_config = TlsConfig::new();
+    tls_config.ca_file("/nonexistent/ca.pem");
+
+    let mut endpoint = Endpoint::new("https://etcdsecure:2379");
+    endpoint.tls_config(tls_config);
+
+    match ClientBuilder::from_endpoints(&[endpoint]).build_per_endpoint() {
+        Err(Error::Tls(_)) => {}
+        result => panic!("expected a TLS error, got {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn no_endpoints() {
+    match ClientBuilder::from_endpoints(&[]).build() {
+        Err(Error::NoEndpoints) => {}
+        result => panic!("expected Error::NoEndpoints, got {:?}", result.map(|_| ())),
+    }
+}
+
+#[tokio::test]
+async fn path_prefix() {
+    let (url, requests) = recording_member();
+    let mut endpoint = Endpoint::new(&url);
+    endpoint.path_prefix("/etcd/");
+
+    let client = ClientBuilder::from_endpoints(&[endpoint]).build().unwrap();
+
+    assert!(client.endpoints()[0].ends_with("/etcd/"));
+
+    client.health().next().await.unwrap().unwrap();
+    assert_eq!(request_path(&requests.recv().unwrap()), "/etcd/health");
+}
+
+#[tokio::test]
+async fn base_path() {
+    let (url, requests) = recording_member();
+    let client = ClientBuilder::new(&[&format!("{}/etcd", url)])
+        .build()
+        .unwrap();
+
+    client.health().next().await.unwrap().unwrap();
+    assert_eq!(request_path(&requests.recv().unwrap()), "/etcd/health");
+
+    // The fake member's answers are not valid responses to these API calls.
+    kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap_err();
+    assert!(request_path(&requests.recv().unwrap()).starts_with("/etcd/v2/keys/test/foo"));
+
+    members::list(&client).await.unwrap_err();
+    assert_eq!(request_path(&requests.recv().unwrap()), "/etcd/v2/members");
+
+    stats::leader_stats(&client).await.unwrap_err();
+    assert_eq!(
+        request_path(&requests.recv().unwrap()),
+        "/etcd/v2/stats/leader"
+    );
+}
+
+#[tokio::test]
+async fn per_endpoint_credentials() {
+    let (first_url, first_requests) = recording_member();
+    let (second_url, second_requests) = recording_member();
+    let mut first = Endpoint::new(&first_url);
+    first.basic_auth(basic_auth("first"));
+
+    let client = ClientBuilder::from_endpoints(&[first, Endpoint::new(&second_url)])
+        .basic_auth(basic_auth("second"))
+        .build()
+        .unwrap();
+
+    let responses: Vec<_> = client.health().collect().await;
+    assert!(responses.iter().all(Result::is_ok));
+
+    assert_eq!(
+        header(&first_requests.recv().unwrap(), "authorization").unwrap(),
+        "Basic cm9vdDpmaXJzdA=="
+    );
+    assert_eq!(
+        header(&second_requests.recv().unwrap(), "authorization").unwrap(),
+        "Basic cm9vdDpzZWNvbmQ="
+    );
+}
+
+#[tokio::test]
+async fn weights_choose_weighted_selector() {
+    let (first_url, first_requests) = recording_member();
+    let (second_url, second_requests) = recording_member();
+    let mut first = Endpoint::new(&first_url);
+    first.weight(0);
+    let mut second = Endpoint::new(&second_url);
+    second.weight(1);
+
+    let client = ClientBuilder::from_endpoints(&[first, second])
+        .build()
+        .unwrap();
+
+    // The fake member's answer is not a valid key response, which does not fail over.
+    kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap_err();
+
+    assert!(request_path(&second_requests.recv().unwrap()).starts_with("/v2/keys/test/foo"));
+    assert!(first_requests.try_recv().is_err());
+}
+
+fn basic_auth(password: &str) -> BasicAuth {
+    BasicAuth {
+        username: "root".to_string(),
+        password: password.to_string(),
+    }
+}
@@ -0,0 +1,196 @@
+use std::net::TcpListener;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use etcd::health::{self, CircuitBreaker, EndpointState};
+use etcd::{members, Client, ClientBuilder};
+use hyper::client::HttpConnector;
+use tokio::time::delay_for;
+
+#[tokio::test]
+async fn open_breakers_are_skipped() {
+    // A listener that never accepts connections, so requests to it never receive a response.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+    let client = ClientBuilder::new(&[&endpoint, "http://etcd:2379"])
+        .request_timeout(Duration::from_millis(500))
+        .circuit_breaker(CircuitBreaker {
+            failure_threshold: 1,
+            reset_timeout: Duration::from_secs(60),
+        })
+        .build()
+        .unwrap();
+
+    members::list(&client).await.unwrap();
+
+    assert_eq!(
+        health::states(&client),
+        vec![
+            (endpoint.clone(), EndpointState::Open),
+            ("http://etcd:2379/".to_string(), EndpointState::Closed),
+        ]
+    );
+
+    let start = Instant::now();
+    members::list(&client).await.unwrap();
+
+    assert!(start.elapsed() < Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn open_breakers_become_half_open() {
+    let client = ClientBuilder::new(&[&closed_endpoint(), "http://etcd:2379"])
+        .circuit_breaker(CircuitBreaker {
+            failure_threshold: 1,
+            reset_timeout: Duration::from_millis(100),
+        })
+        .build()
+        .unwrap();
+
+    members::list(&client).await.unwrap();
+    assert_eq!(health::states(&client)[0].1, EndpointState::Open);
+
+    delay_for(Duration::from_millis(150)).await;
+    assert_eq!(health::states(&client)[0].1, EndpointState::HalfOpen);
+}
+
+#[tokio::test]
+async fn breakers_open_after_failure_threshold() {
+    let client = ClientBuilder::new(&[&closed_endpoint(), "http://etcd:2379"])
+        .circuit_breaker(CircuitBreaker {
+            failure_threshold: 2,
+            reset_timeout: Duration::from_secs(60),
+        })
+        .build()
+        .unwrap();
+
+    members::list(&client).await.unwrap();
+    assert_eq!(health::states(&client)[0].1, EndpointState::Closed);
+
+    members::list(&client).await.unwrap();
+    assert_eq!(health::states(&client)[0].1, EndpointState::Open);
+}
+
+#[tokio::test]
+async fn probe() {
+    let endpoint = closed_endpoint();
+    let client = client_with_breaker(&endpoint);
+
+    let states = health::probe(&client).await;
+
+    assert_eq!(
+        states,
+        vec![
+            (endpoint, EndpointState::Open),
+            ("http://etcd:2379/".to_string(), EndpointState::Closed),
+        ]
+    );
+    assert_eq!(health::states(&client), states);
+}
+
+#[tokio::test]
+async fn probe_without_circuit_breaker() {
+    let client = Client::new(&[&closed_endpoint(), "http://etcd:2379"], None).unwrap();
+
+    for (_, state) in health::probe(&client).await {
+        assert_eq!(state, EndpointState::Closed);
+    }
+}
+
+#[tokio::test]
+async fn spawn_probe() {
+    let client = client_with_breaker(&closed_endpoint());
+    let _handle = health::spawn_probe(&client, Duration::from_secs(60));
+
+    for _ in 0..50 {
+        if health::states(&client)[0].1 == EndpointState::Open {
+            break;
+        }
+
+        delay_for(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(health::states(&client)[0].1, EndpointState::Open);
+    assert_eq!(health::states(&client)[1].1, EndpointState::Closed);
+}
+
+#[tokio::test]
+async fn half_open_breakers_are_tried_when_reached() {
+    let endpoint = closed_endpoint();
+    let client = ClientBuilder::new(&["http://etcd:2379", &endpoint])
+        .circuit_breaker(CircuitBreaker {
+            failure_threshold: 1,
+            reset_timeout: Duration::from_millis(100),
+        })
+        .build()
+        .unwrap();
+
+    health::probe(&client).await;
+    delay_for(Duration::from_millis(150)).await;
+    assert_eq!(health::states(&client)[1].1, EndpointState::HalfOpen);
+
+    // The first member answers, so the half-open one is never reached and keeps its trial call.
+    members::list(&client).await.unwrap();
+
+    client
+        .set_endpoints(&[&endpoint, "http://etcd:2379"])
+        .unwrap();
+    members::list(&client).await.unwrap();
+
+    assert_eq!(health::states(&client)[0].1, EndpointState::Open);
+}
+
+#[test]
+fn states_while_endpoints_change() {
+    let endpoint = closed_endpoint();
+    let client = client_with_breaker(&endpoint);
+    let (sender, receiver) = channel();
+
+    let reader = client.clone();
+    let sender_for_reader = sender.clone();
+    thread::spawn(move || {
+        for _ in 0..10_000 {
+            health::states(&reader);
+        }
+
+        sender_for_reader.send(()).unwrap();
+    });
+
+    thread::spawn(move || {
+        for i in 0..10_000 {
+            if i % 2 == 0 {
+                client.set_endpoints(&["http://etcd:2379"]).unwrap();
+            } else {
+                client.add_endpoint(&endpoint).unwrap();
+            }
+        }
+
+        sender.send(()).unwrap();
+    });
+
+    for _ in 0..2 {
+        receiver
+            .recv_timeout(Duration::from_secs(30))
+            .expect("expected neither thread to deadlock");
+    }
+}
+
+/// Returns the endpoint of a listener that has been closed, so connections to it are refused.
+fn closed_endpoint() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+    format!("http://{}/", listener.local_addr().unwrap())
+}
+
+/// Returns a client whose breakers open on the first failure.
+fn client_with_breaker(endpoint: &str) -> Client<HttpConnector> {
+    ClientBuilder::new(&[endpoint, "http://etcd:2379"])
+        .circuit_breaker(CircuitBreaker {
+            failure_threshold: 1,
+            reset_timeout: Duration::from_secs(60),
+        })
+        .build()
+        .unwrap()
+}
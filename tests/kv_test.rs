@@ -1,8 +1,17 @@
+#![allow(clippy::bool_assert_comparison, clippy::needless_borrow)]
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
 use std::time::Duration;
 
-use etcd::kv::{self, Action, GetOptions, KeyValueInfo, WatchError, WatchOptions};
-use etcd::{Error, Response};
+use etcd::health::{self, CircuitBreaker, EndpointState};
+use etcd::kv::{
+    self, Action, GetOptions, KeyPath, KeyValueInfo, ReadConsistency, WatchError, WatchOptions,
+};
+use etcd::{Client, ClientBuilder, Error, Response};
 use futures::future::try_join_all;
+use futures::stream::StreamExt;
 use tokio::task::spawn;
 use tokio::time::delay_for;
 
@@ -32,15 +41,13 @@ async fn create_does_not_replace_existing_key() {
         .await
         .unwrap();
 
-    let errors = kv::create(&client, "/test/foo", "bar", Some(60))
+    let error = kv::create(&client, "/test/foo", "bar", Some(60))
         .await
         .expect_err("expected EtcdError due to pre-existing key");
 
-    for error in errors {
-        match error {
-            Error::Api(ref error) => assert_eq!(error.message, "Key already exists"),
-            _ => panic!("expected EtcdError due to pre-existing key"),
-        }
+    match error {
+        Error::Api(ref error) => assert_eq!(error.message, "Key already exists"),
+        _ => panic!("expected EtcdError due to pre-existing key"),
     }
 }
 
@@ -69,6 +76,85 @@ async fn create_in_order_must_operate_on_a_directory() {
         .unwrap_err();
 }
 
+#[tokio::test]
+async fn create_in_order_is_not_retried_after_timeout() {
+    let client = TestClient::new().await;
+
+    // A listener that never accepts connections, so requests to it never receive a response.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+    let unresponsive_client = ClientBuilder::new(&[&endpoint, "http://etcd:2379"])
+        .request_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    let error = kv::create_in_order(&unresponsive_client, "/test/foo", "bar", None)
+        .await
+        .expect_err("expected Error::Indeterminate");
+
+    match error {
+        Error::Indeterminate(ref error) => match **error {
+            Error::Timeout => {}
+            ref error => panic!("expected a timeout, got {:?}", error),
+        },
+        error => panic!("expected Error::Indeterminate, got {:?}", error),
+    }
+
+    kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .expect_err("expected the write not to be made again");
+}
+
+#[tokio::test]
+async fn delete_is_not_retried_after_timeout() {
+    let client = TestClient::new().await;
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    // A listener that never accepts connections, so requests to it never receive a response.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+    let unresponsive_client = ClientBuilder::new(&[&endpoint, "http://etcd:2379"])
+        .request_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    match kv::delete(&unresponsive_client, "/test/foo", false).await {
+        Err(Error::Indeterminate(_)) => {}
+        result => panic!("expected Error::Indeterminate, got {:?}", result),
+    }
+
+    kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .expect("expected the delete not to be made again");
+}
+
+#[tokio::test]
+async fn create_in_order_is_retried_after_connection_failure() {
+    let client = TestClient::new().await;
+
+    // A listener that is closed right away, so connections to it are refused.
+    let endpoint = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/", listener.local_addr().unwrap())
+    };
+
+    let failover_client = ClientBuilder::new(&[&endpoint, "http://etcd:2379"])
+        .build()
+        .unwrap();
+
+    kv::create_in_order(&failover_client, "/test/foo", "bar", None)
+        .await
+        .unwrap();
+
+    let res = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(res.data.node.nodes.unwrap().len(), 1);
+}
+
 #[tokio::test]
 async fn compare_and_delete() {
     let client = TestClient::new().await;
@@ -108,17 +194,13 @@ async fn compare_and_delete_requires_conditions() {
     let client = TestClient::new().await;
 
     kv::create(&client, "/test/foo", "bar", None).await.unwrap();
-    let errors = kv::compare_and_delete(&client, "/test/foo", None, None)
+    let error = kv::compare_and_delete(&client, "/test/foo", None, None)
         .await
         .expect_err("expected Error::InvalidConditions");
 
-    if errors.len() == 1 {
-        match errors[0] {
-            Error::InvalidConditions => {}
-            _ => panic!("expected Error::InvalidConditions"),
-        }
-    } else {
-        panic!("expected a single error: Error::InvalidConditions");
+    match error {
+        Error::InvalidConditions => {}
+        _ => panic!("expected Error::InvalidConditions"),
     }
 }
 
@@ -160,17 +242,13 @@ async fn test_compare_and_swap_only_value() {
 async fn compare_and_swap_requires_conditions() {
     let client = TestClient::new().await;
     kv::create(&client, "/test/foo", "bar", None).await.unwrap();
-    let errors = kv::compare_and_swap(&client, "/test/foo", "baz", None, None, None)
+    let error = kv::compare_and_swap(&client, "/test/foo", "baz", None, None, None)
         .await
         .expect_err("expected Error::InvalidConditions");
 
-    if errors.len() == 1 {
-        match errors[0] {
-            Error::InvalidConditions => {}
-            _ => panic!("expected Error::InvalidConditions"),
-        }
-    } else {
-        panic!("expected a single error: Error::InvalidConditions");
+    match error {
+        Error::InvalidConditions => {}
+        _ => panic!("expected Error::InvalidConditions"),
     }
 }
 
@@ -247,6 +325,61 @@ async fn get_recursive() {
     );
 }
 
+#[tokio::test]
+async fn get_nodes() {
+    let client = TestClient::new().await;
+
+    kv::set(&client, "/test/dir/foo", "1", None).await.unwrap();
+    kv::set(&client, "/test/dir/sub/bar", "2", None)
+        .await
+        .unwrap();
+
+    let nodes: Vec<_> = kv::get_nodes(
+        &client,
+        "/test/dir",
+        GetOptions {
+            recursive: true,
+            sort: true,
+            ..Default::default()
+        },
+    )
+    .map(Result::unwrap)
+    .collect()
+    .await;
+    let keys: Vec<_> = nodes
+        .iter()
+        .map(|node| node.key.as_deref().unwrap())
+        .collect();
+
+    assert_eq!(
+        keys,
+        [
+            "/test/dir/foo",
+            "/test/dir/sub/bar",
+            "/test/dir/sub",
+            "/test/dir"
+        ]
+    );
+    assert_eq!(nodes[1].value.as_deref(), Some("2"));
+    assert!(nodes[2].dir.unwrap());
+    assert!(nodes[2].nodes.is_none());
+}
+
+#[tokio::test]
+async fn get_nodes_missing_key() {
+    let client = TestClient::no_destructor();
+
+    let results: Vec<_> = kv::get_nodes(&client, "/test/missing", GetOptions::default())
+        .collect()
+        .await;
+
+    assert_eq!(results.len(), 1);
+    match results[0] {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 100),
+        ref result => panic!("expected Error::Api, got {:?}", result),
+    }
+}
+
 #[tokio::test]
 async fn get_root() {
     let client = TestClient::new().await;
@@ -265,6 +398,7 @@ async fn get_root() {
     assert_eq!(node.dir.unwrap(), true);
 }
 
+#[cfg(feature = "tls")]
 #[tokio::test]
 async fn https() {
     let client = TestClient::https(true);
@@ -273,6 +407,27 @@ async fn https() {
         .unwrap();
 }
 
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn https_with_config() {
+    let client = TestClient::https_with_config(true);
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn https_with_config_without_client_certificate() {
+    let client = TestClient::https_with_config(false);
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap_err();
+}
+
+#[cfg(feature = "tls")]
 #[tokio::test]
 async fn https_without_valid_client_certificate() {
     let client = TestClient::https(false);
@@ -362,16 +517,167 @@ async fn update() {
 async fn update_requires_existing_key() {
     let client = TestClient::new().await;
 
-    let errors = kv::update(&client, "/test/foo", "bar", None)
+    let error = kv::update(&client, "/test/foo", "bar", None)
         .await
         .expect_err("expected EtcdError due to missing key");
 
-    match errors[0] {
+    match error {
         Error::Api(ref error) => assert_eq!(error.message, "Key not found"),
         _ => panic!("expected EtcdError due to missing key"),
     }
 }
 
+#[tokio::test]
+async fn logical_errors_do_not_fail_over() {
+    let client = Client::new(&["http://etcd:2379", "http://unreachable:2379"], None).unwrap();
+
+    let error = kv::get(&client, "/test/missing", GetOptions::default())
+        .await
+        .expect_err("expected EtcdError due to missing key");
+
+    match error {
+        Error::Api(ref error) => assert_eq!(error.message, "Key not found"),
+        error => panic!("expected EtcdError due to missing key, got {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn monotonic_reads() {
+    let _client = TestClient::new().await;
+    let lagging_endpoint = lagging_member();
+
+    let client = ClientBuilder::new(&[&lagging_endpoint, "http://etcd:2379"])
+        .monotonic_reads(true)
+        .build()
+        .unwrap();
+
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    let res = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(res.data.node.value.unwrap(), "bar");
+    assert!(res.cluster_info.etcd_index.unwrap() >= client.etcd_index().unwrap());
+}
+
+#[tokio::test]
+async fn stale_reads_are_not_failures() {
+    let _client = TestClient::new().await;
+    let lagging_endpoint = lagging_member();
+
+    let client = ClientBuilder::new(&[&lagging_endpoint, "http://etcd:2379"])
+        .circuit_breaker(CircuitBreaker {
+            failure_threshold: 2,
+            reset_timeout: Duration::from_secs(60),
+        })
+        .monotonic_reads(true)
+        .build()
+        .unwrap();
+
+    // The lagging member fails the write, and then answers the read with a stale value.
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+    kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(health::states(&client)[0].1, EndpointState::Closed);
+}
+
+#[tokio::test]
+async fn monotonic_get() {
+    let _client = TestClient::new().await;
+    let lagging_endpoint = lagging_member();
+
+    let client = Client::new(&[&lagging_endpoint, "http://etcd:2379"], None).unwrap();
+
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    let res = kv::get(
+        &client,
+        "/test/foo",
+        GetOptions {
+            consistency: ReadConsistency::Monotonic,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(res.data.node.value.unwrap(), "bar");
+}
+
+#[tokio::test]
+async fn quorum_get() {
+    let client = TestClient::new().await;
+
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    let res = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+    let raft_index = res.cluster_info.raft_index.unwrap();
+
+    let res = kv::get(
+        &client,
+        "/test/foo",
+        GetOptions {
+            consistency: ReadConsistency::Quorum,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(res.data.node.value.unwrap(), "bar");
+
+    // A quorum read goes through the Raft log, so it advances the Raft index.
+    assert!(res.cluster_info.raft_index.unwrap() > raft_index);
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn strong_consistency_get() {
+    let client = TestClient::new().await;
+
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    let res = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+    let raft_index = res.cluster_info.raft_index.unwrap();
+
+    let res = kv::get(
+        &client,
+        "/test/foo",
+        GetOptions {
+            strong_consistency: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(res.data.node.value.unwrap(), "bar");
+    assert!(res.cluster_info.raft_index.unwrap() > raft_index);
+}
+
+#[tokio::test]
+async fn reads_are_not_monotonic_by_default() {
+    let _client = TestClient::new().await;
+    let lagging_endpoint = lagging_member();
+
+    let client = Client::new(&[&lagging_endpoint, "http://etcd:2379"], None).unwrap();
+
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    let res = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(res.data.node.value.unwrap(), "old");
+}
+
 #[tokio::test]
 async fn update_dir() {
     let client = TestClient::new().await;
@@ -447,6 +753,59 @@ async fn watch() {
     child.await.unwrap()
 }
 
+#[tokio::test]
+async fn watch_monotonic() {
+    let client = TestClient::new().await;
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    // A change the client hasn't observed.
+    let other_client = TestClient::no_destructor();
+    kv::set(&other_client, "/test/foo", "baz", None)
+        .await
+        .unwrap();
+
+    let res = kv::watch(
+        &client,
+        "/test/foo",
+        WatchOptions {
+            consistency: ReadConsistency::Monotonic,
+            timeout: Some(Duration::from_millis(1000)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(res.data.node.value.unwrap(), "baz");
+}
+
+#[tokio::test]
+async fn watch_quorum() {
+    let client = TestClient::new().await;
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    let child = spawn(async {
+        let client = TestClient::no_destructor();
+        delay_for(Duration::from_millis(100)).await;
+        kv::set(&client, "/test/foo", "baz", None).await.unwrap();
+    });
+
+    let res = kv::watch(
+        &client,
+        "/test/foo",
+        WatchOptions {
+            consistency: ReadConsistency::Quorum,
+            timeout: Some(Duration::from_millis(1000)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(res.data.node.value.unwrap(), "baz");
+    child.await.unwrap()
+}
+
 #[tokio::test]
 async fn watch_cancel() {
     let client = TestClient::new().await;
@@ -519,3 +878,116 @@ async fn watch_recursive() {
     assert_eq!(node.value.unwrap(), "baz");
     child.await.unwrap();
 }
+
+#[tokio::test]
+async fn keys_with_special_characters() {
+    let client = TestClient::new().await;
+
+    for key in &[
+        "/test/foo bar",
+        "/test/foo?bar=baz",
+        "/test/foo#bar",
+        "/test/100%",
+        "/test/a+b&c",
+        "/test/ключ",
+    ] {
+        kv::set(&client, key, "bar", None).await.unwrap();
+
+        let res = kv::get(&client, key, GetOptions::default()).await.unwrap();
+        assert_eq!(res.data.node.key.unwrap(), *key);
+        assert_eq!(res.data.node.value.unwrap(), "bar");
+
+        kv::delete(&client, key, false).await.unwrap();
+        kv::get(&client, key, GetOptions::default())
+            .await
+            .unwrap_err();
+    }
+}
+
+#[tokio::test]
+async fn watch_key_with_special_characters() {
+    let client = TestClient::new().await;
+    kv::create(&client, "/test/foo bar?", "bar", None)
+        .await
+        .unwrap();
+    let child = spawn(async {
+        let client = TestClient::no_destructor();
+        kv::set(&client, "/test/foo bar?", "baz", None)
+            .await
+            .unwrap();
+    });
+
+    let res = kv::watch(&client, "/test/foo bar?", WatchOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(res.data.node.key.unwrap(), "/test/foo bar?");
+    assert_eq!(res.data.node.value.unwrap(), "baz");
+    child.await.unwrap()
+}
+
+#[tokio::test]
+async fn invalid_keys_are_rejected() {
+    let client = TestClient::no_destructor();
+
+    for key in &[
+        "/test//foo",
+        "/test/./foo",
+        "/test/../foo",
+        "/test/..",
+        "//",
+    ] {
+        match kv::set(&client, key, "bar", None).await {
+            Err(Error::InvalidKey(_)) => {}
+            result => panic!("expected Error::InvalidKey for {:?}, got {:?}", key, result),
+        }
+    }
+}
+
+#[test]
+fn key_path() {
+    assert_eq!(KeyPath::new("foo").unwrap().as_str(), "/foo");
+    assert_eq!(KeyPath::new("/").unwrap().as_str(), "/");
+    assert_eq!(KeyPath::new("/foo/").unwrap().as_str(), "/foo/");
+    assert_eq!(
+        KeyPath::new("/foo bar/ключ/a%b").unwrap().encoded(),
+        "/foo%20bar/%D0%BA%D0%BB%D1%8E%D1%87/a%25b"
+    );
+    assert_eq!(KeyPath::new("/a-b.c_d~e").unwrap().encoded(), "/a-b.c_d~e");
+}
+
+/// Starts a fake cluster member that is behind the rest of the cluster: it answers every read
+/// with the value "old" at etcd index 1, and every write with a server error.
+fn lagging_member() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buffer = [0; 4096];
+            let length = stream.read(&mut buffer).unwrap();
+
+            let response = if buffer[..length].starts_with(b"GET") {
+                let body = concat!(
+                    r#"{"action":"get","node":{"key":"/test/foo","value":"old","#,
+                    r#""modifiedIndex":1,"createdIndex":1}}"#
+                );
+
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nX-Etcd-Index: 1\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\
+                 Connection: close\r\n\r\n"
+                    .to_string()
+            };
+
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    endpoint
+}
@@ -0,0 +1,265 @@
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use etcd::kv::{self, GetOptions};
+use etcd::middleware::{Layer, Service, Transport};
+use etcd::{BasicAuth, ClientBuilder, Error};
+use futures::future::{self, BoxFuture, FutureExt};
+use hyper::header::AUTHORIZATION;
+use hyper::{Body, Request, Response, StatusCode};
+
+use crate::test::TestClient;
+
+mod test;
+
+#[tokio::test]
+async fn layers_see_requests_in_order() {
+    let _cleanup = TestClient::new().await;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let client = ClientBuilder::new(&["http://etcd:2379"])
+        .basic_auth(BasicAuth {
+            username: "root".to_string(),
+            password: "secret".to_string(),
+        })
+        .layer(RecordLayer::new("outer", &seen))
+        .layer(RecordLayer::new("inner", &seen))
+        .build()
+        .unwrap();
+
+    kv::set(&client, "/test/foo", "bar", None).await.unwrap();
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            "outer PUT /v2/keys/test/foo Basic cm9vdDpzZWNyZXQ=",
+            "inner PUT /v2/keys/test/foo Basic cm9vdDpzZWNyZXQ=",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn outer_layers_see_requests_once() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let calls = Arc::new(Mutex::new(0));
+    let mock_calls = calls.clone();
+
+    let client = ClientBuilder::new(&["http://etcd.invalid:2379"])
+        .basic_auth(BasicAuth {
+            username: "root".to_string(),
+            password: "secret".to_string(),
+        })
+        .max_retries(1)
+        .outer_layer(RecordLayer::new("outer", &seen))
+        .layer(MockLayer::new(move |_| {
+            let mut calls = mock_calls.lock().unwrap();
+            *calls += 1;
+
+            if *calls == 1 {
+                Ok(json(StatusCode::INTERNAL_SERVER_ERROR, ""))
+            } else {
+                Ok(json(
+                    StatusCode::OK,
+                    r#"{"action":"get","node":{"key":"/test/foo","value":"bar"}}"#,
+                ))
+            }
+        }))
+        .build()
+        .unwrap();
+
+    kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(*calls.lock().unwrap(), 2);
+    assert_eq!(*seen.lock().unwrap(), vec!["outer GET /v2/keys/test/foo "]);
+}
+
+#[tokio::test]
+async fn mock_transport() {
+    let client = ClientBuilder::new(&["http://etcd.invalid:2379"])
+        .layer(MockLayer::new(|_| {
+            Ok(json(
+                StatusCode::OK,
+                r#"{"action":"get","node":{"key":"/test/foo","value":"bar"}}"#,
+            ))
+        }))
+        .build()
+        .unwrap();
+
+    let response = kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(response.data.node.value.unwrap(), "bar");
+}
+
+#[tokio::test]
+async fn layer_errors() {
+    let client = ClientBuilder::new(&["http://etcd.invalid:2379"])
+        .layer(MockLayer::new(|_| {
+            Err(Error::Middleware("rate limited".into()))
+        }))
+        .build()
+        .unwrap();
+
+    match kv::get(&client, "/test/foo", GetOptions::default()).await {
+        Err(Error::Middleware(ref error)) => assert_eq!(error.to_string(), "rate limited"),
+        result => panic!("expected a middleware error, got {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn retries_go_through_layers() {
+    let calls = Arc::new(Mutex::new(0));
+    let mock_calls = calls.clone();
+
+    let client = ClientBuilder::new(&["http://etcd.invalid:2379"])
+        .max_retries(1)
+        .layer(MockLayer::new(move |_| {
+            let mut calls = mock_calls.lock().unwrap();
+            *calls += 1;
+
+            if *calls == 1 {
+                Ok(json(StatusCode::INTERNAL_SERVER_ERROR, ""))
+            } else {
+                Ok(json(
+                    StatusCode::OK,
+                    r#"{"action":"get","node":{"key":"/test/foo","value":"bar"}}"#,
+                ))
+            }
+        }))
+        .build()
+        .unwrap();
+
+    kv::get(&client, "/test/foo", GetOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(*calls.lock().unwrap(), 2);
+
+    // A request that is not idempotent is not made again after reaching the member.
+    *calls.lock().unwrap() = 0;
+
+    match kv::create_in_order(&client, "/test/foo", "bar", None).await {
+        Err(Error::Indeterminate(_)) => {}
+        result => panic!("expected an indeterminate result, got {:?}", result),
+    }
+    assert_eq!(*calls.lock().unwrap(), 1);
+}
+
+/// Constructs a JSON response.
+fn json(status: StatusCode, body: &'static str) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+}
+
+/// A layer that records the requests passing through it.
+struct RecordLayer {
+    name: &'static str,
+    seen: Arc<Mutex<Vec<String>>>,
+}
+
+impl RecordLayer {
+    fn new(name: &'static str, seen: &Arc<Mutex<Vec<String>>>) -> Self {
+        RecordLayer {
+            name,
+            seen: seen.clone(),
+        }
+    }
+}
+
+impl Layer<Transport> for RecordLayer {
+    type Service = Record;
+
+    fn layer(&self, inner: Transport) -> Record {
+        Record {
+            inner,
+            name: self.name,
+            seen: self.seen.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Record {
+    inner: Transport,
+    name: &'static str,
+    seen: Arc<Mutex<Vec<String>>>,
+}
+
+impl Service<Request<Body>> for Record {
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Response<Body>, Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let authorization = request
+            .headers()
+            .get(AUTHORIZATION)
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default();
+
+        self.seen.lock().unwrap().push(format!(
+            "{} {} {} {}",
+            self.name,
+            request.method(),
+            request.uri().path(),
+            authorization
+        ));
+
+        self.inner.call(request)
+    }
+}
+
+/// Answers a request instead of the cluster.
+type Answer = Arc<dyn Fn(&Request<Body>) -> Result<Response<Body>, Error> + Send + Sync>;
+
+/// A layer that answers requests itself instead of sending them to the cluster.
+struct MockLayer {
+    answer: Answer,
+}
+
+impl MockLayer {
+    fn new<F>(answer: F) -> Self
+    where
+        F: Fn(&Request<Body>) -> Result<Response<Body>, Error> + Send + Sync + 'static,
+    {
+        MockLayer {
+            answer: Arc::new(answer),
+        }
+    }
+}
+
+impl Layer<Transport> for MockLayer {
+    type Service = Mock;
+
+    fn layer(&self, _: Transport) -> Mock {
+        Mock {
+            answer: self.answer.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Mock {
+    answer: Answer,
+}
+
+impl Service<Request<Body>> for Mock {
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Response<Body>, Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        future::ready((self.answer)(&request)).boxed()
+    }
+}
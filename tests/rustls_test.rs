@@ -0,0 +1,101 @@
+#![cfg(feature = "rustls")]
+
+use etcd::kv;
+use etcd::tls::TlsConfig;
+use etcd::{Client, Error};
+
+use crate::test::{tls_config, TestClient};
+
+mod test;
+
+#[tokio::test]
+async fn https() {
+    let client = TestClient::https_rustls(true);
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn https_without_valid_client_certificate() {
+    let client = TestClient::https_rustls(false);
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn server_name() {
+    let mut config = tls_config(true);
+    let client =
+        Client::https_rustls_with_config(&["https://127.0.0.2:2379"], None, &config).unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap_err();
+
+    config.server_name("etcdsecure");
+    let client =
+        Client::https_rustls_with_config(&["https://127.0.0.2:2379"], None, &config).unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn insecure_skip_verify() {
+    let mut config = TlsConfig::new();
+    config
+        .cert_file("/source/tests/ssl/client.pem")
+        .key_file("/source/tests/ssl/client-key.pem");
+    let client =
+        Client::https_rustls_with_config(&["https://etcdsecure:2379"], None, &config).unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap_err();
+
+    config.insecure_skip_verify(true);
+    let client =
+        Client::https_rustls_with_config(&["https://etcdsecure:2379"], None, &config).unwrap();
+
+    kv::set(&client, "/test/foo", "bar", Some(60))
+        .await
+        .unwrap();
+}
+
+#[test]
+fn invalid_ca() {
+    let mut config = TlsConfig::new();
+    config.ca_pem(b"not a certificate");
+
+    match Client::https_rustls_with_config(&["https://etcdsecure:2379"], None, &config) {
+        Err(Error::Tls(_)) => {}
+        result => panic!("expected a TLS error, got {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn missing_file() {
+    let mut config = TlsConfig::new();
+    config.ca_file("/nonexistent/ca.pem");
+
+    match Client::https_rustls_with_config(&["https://etcdsecure:2379"], None, &config) {
+        Err(Error::Tls(_)) => {}
+        result => panic!("expected a TLS error, got {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn cert_without_key() {
+    let mut config = TlsConfig::new();
+    config.cert_file("/source/tests/ssl/client.pem");
+
+    match Client::https_rustls_with_config(&["https://etcdsecure:2379"], None, &config) {
+        Err(Error::Tls(_)) => {}
+        result => panic!("expected a TLS error, got {:?}", result.map(|_| ())),
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use etcd::selector::{
+    EndpointSelector, InOrder, LeastLatency, Random, RoundRobin, Sticky, Weighted,
+};
+use hyper::Uri;
+
+fn endpoints() -> Vec<Uri> {
+    vec![
+        "http://etcd1:2379/".parse().unwrap(),
+        "http://etcd2:2379/".parse().unwrap(),
+        "http://etcd3:2379/".parse().unwrap(),
+    ]
+}
+
+#[test]
+fn in_order() {
+    let endpoints = endpoints();
+
+    assert_eq!(InOrder.order(&endpoints), endpoints);
+    assert_eq!(InOrder.order(&endpoints), endpoints);
+}
+
+#[test]
+fn round_robin() {
+    let endpoints = endpoints();
+    let selector = RoundRobin::new();
+
+    assert_eq!(selector.order(&endpoints)[0], endpoints[0]);
+    assert_eq!(selector.order(&endpoints)[0], endpoints[1]);
+    assert_eq!(selector.order(&endpoints)[0], endpoints[2]);
+    assert_eq!(selector.order(&endpoints)[0], endpoints[0]);
+    assert_eq!(selector.order(&endpoints).len(), 3);
+}
+
+#[test]
+fn random() {
+    let endpoints = endpoints();
+    let mut order = Random.order(&endpoints);
+
+    order.sort_by_key(|endpoint| endpoint.to_string());
+
+    assert_eq!(order, endpoints);
+}
+
+#[test]
+fn sticky() {
+    let endpoints = endpoints();
+    let selector = Sticky::new();
+
+    selector.failed(&endpoints[0]);
+    selector.succeeded(&endpoints[1], Duration::from_millis(1));
+
+    assert_eq!(selector.order(&endpoints)[0], endpoints[1]);
+    assert_eq!(selector.order(&endpoints)[0], endpoints[1]);
+
+    selector.failed(&endpoints[1]);
+    selector.succeeded(&endpoints[2], Duration::from_millis(1));
+
+    assert_eq!(selector.order(&endpoints)[0], endpoints[2]);
+}
+
+#[test]
+fn least_latency() {
+    let endpoints = endpoints();
+    let selector = LeastLatency::new();
+
+    selector.succeeded(&endpoints[0], Duration::from_millis(30));
+    selector.succeeded(&endpoints[1], Duration::from_millis(10));
+
+    // The unmeasured endpoint is tried first so its latency becomes known.
+    assert_eq!(
+        selector.order(&endpoints),
+        vec![
+            endpoints[2].clone(),
+            endpoints[1].clone(),
+            endpoints[0].clone()
+        ]
+    );
+
+    selector.failed(&endpoints[2]);
+
+    assert_eq!(
+        selector.order(&endpoints),
+        vec![
+            endpoints[1].clone(),
+            endpoints[0].clone(),
+            endpoints[2].clone()
+        ]
+    );
+}
+
+#[test]
+fn weighted() {
+    let endpoints = endpoints();
+    let mut selector = Weighted::new();
+
+    selector
+        .weight(endpoints[0].clone(), 0)
+        .weight(endpoints[1].clone(), 1000);
+
+    let mut first = 0;
+
+    for _ in 0..100 {
+        let order = selector.order(&endpoints);
+
+        assert_eq!(order.len(), 3);
+        assert_eq!(order[2], endpoints[0]);
+
+        if order[0] == endpoints[1] {
+            first += 1;
+        }
+    }
+
+    assert!(first > 90);
+}
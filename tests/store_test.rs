@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use etcd::kv::{Action, GetOptions, WatchError, WatchOptions};
+use etcd::store::{KeyValueStore, MemoryStore};
+use etcd::{Client, Error};
+use tokio::time::{advance, delay_for, pause};
+
+#[tokio::test]
+async fn client() {
+    let client = Client::new(&["http://etcd:2379"], None).unwrap();
+    client.delete("/test/store", true).await.ok();
+
+    behaves_like_etcd(&client).await;
+}
+
+#[tokio::test]
+async fn memory_store() {
+    behaves_like_etcd(&MemoryStore::new()).await;
+}
+
+/// Checks the behavior both backends share.
+async fn behaves_like_etcd(store: &dyn KeyValueStore) {
+    let response = store.create("/test/store/foo", "bar", None).await.unwrap();
+    assert_eq!(response.data.action, Action::Create);
+    assert_eq!(response.data.node.value.unwrap(), "bar");
+    let created_index = response.data.node.created_index.unwrap();
+    assert_eq!(response.cluster_info.etcd_index, Some(created_index));
+
+    match store.create("/test/store/foo", "baz", None).await {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 105),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+
+    match store.update("/test/store/missing", "baz", None).await {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 100),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+
+    let response = store
+        .update("/test/store/foo", "baz", Some(60))
+        .await
+        .unwrap();
+    assert_eq!(response.data.action, Action::Update);
+    assert_eq!(response.data.node.created_index.unwrap(), created_index);
+    assert!(response.data.node.ttl.unwrap() > 0);
+    assert_eq!(response.data.prev_node.unwrap().value.unwrap(), "bar");
+    let modified_index = response.data.node.modified_index.unwrap();
+
+    match store
+        .compare_and_swap("/test/store/foo", "qux", None, Some("bar"), None)
+        .await
+    {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 101),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+
+    match store
+        .compare_and_swap("/test/store/foo", "qux", None, None, None)
+        .await
+    {
+        Err(Error::InvalidConditions) => {}
+        result => panic!("expected invalid conditions, got {:?}", result),
+    }
+
+    let response = store
+        .compare_and_swap("/test/store/foo", "qux", None, None, Some(modified_index))
+        .await
+        .unwrap();
+    assert_eq!(response.data.action, Action::CompareAndSwap);
+
+    let response = store.set("/test/store/foo", "set", None).await.unwrap();
+    assert_eq!(response.data.action, Action::Set);
+
+    let response = store
+        .get("/test/store/foo", GetOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(response.data.action, Action::Get);
+    assert_eq!(response.data.node.value.unwrap(), "set");
+
+    match store.delete("/test/store", false).await {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 102),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+
+    let response = store.delete("/test/store/foo", false).await.unwrap();
+    assert_eq!(response.data.action, Action::Delete);
+    assert_eq!(response.data.prev_node.unwrap().value.unwrap(), "set");
+
+    match store.get("/test/store/foo", GetOptions::default()).await {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 100),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+
+    match store.set("/test/store//foo", "bar", None).await {
+        Err(Error::InvalidKey(_)) => {}
+        result => panic!("expected an invalid key, got {:?}", result),
+    }
+
+    let response = store
+        .watch(
+            "/test/store/foo",
+            WatchOptions {
+                index: Some(modified_index),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.data.action, Action::Update);
+    assert_eq!(response.data.node.value.unwrap(), "baz");
+
+    let options = WatchOptions {
+        timeout: Some(Duration::from_millis(50)),
+        ..Default::default()
+    };
+
+    match store.watch("/test/store/foo", options).await {
+        Err(WatchError::Timeout) => {}
+        result => panic!("expected a timeout, got {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn directories() {
+    let store = MemoryStore::new();
+    store.set("/dir/foo", "1", None).await.unwrap();
+    store.set("/dir/sub/bar", "2", None).await.unwrap();
+
+    let response = store.get("/dir", GetOptions::default()).await.unwrap();
+    let nodes = response.data.node.nodes.unwrap();
+    assert_eq!(response.data.node.dir, Some(true));
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[0].key.as_deref(), Some("/dir/foo"));
+    assert_eq!(nodes[1].key.as_deref(), Some("/dir/sub"));
+    assert!(nodes[1].nodes.is_none());
+
+    let options = GetOptions {
+        recursive: true,
+        ..Default::default()
+    };
+    let response = store.get("/", options).await.unwrap();
+    let dir = &response.data.node.nodes.unwrap()[0];
+    let sub = &dir.nodes.as_ref().unwrap()[1];
+    assert_eq!(sub.nodes.as_ref().unwrap()[0].value.as_deref(), Some("2"));
+
+    match store.set("/dir", "value", None).await {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 102),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+
+    match store.set("/dir/foo/bar", "value", None).await {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 104),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+
+    store.delete("/dir", true).await.unwrap();
+
+    match store.get("/dir/sub/bar", GetOptions::default()).await {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 100),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn watch_waits_for_changes() {
+    let store = MemoryStore::new();
+    let writer = store.clone();
+
+    tokio::spawn(async move {
+        delay_for(Duration::from_millis(50)).await;
+        writer.set("/dir/other", "1", None).await.unwrap();
+        writer.set("/dir/sub/foo", "2", None).await.unwrap();
+    });
+
+    let options = WatchOptions {
+        recursive: true,
+        ..Default::default()
+    };
+    let response = store.watch("/dir/sub", options).await.unwrap();
+
+    assert_eq!(response.data.node.key.unwrap(), "/dir/sub/foo");
+    assert_eq!(response.data.node.value.unwrap(), "2");
+}
+
+#[tokio::test]
+async fn ttl() {
+    pause();
+
+    let store = MemoryStore::new();
+    store.set("/foo", "bar", Some(10)).await.unwrap();
+
+    let response = store.get("/foo", GetOptions::default()).await.unwrap();
+    assert_eq!(response.data.node.ttl, Some(10));
+
+    advance(Duration::from_millis(500)).await;
+    let response = store.get("/foo", GetOptions::default()).await.unwrap();
+    assert_eq!(response.data.node.ttl, Some(10));
+
+    let watcher = store.clone();
+    let watch = tokio::spawn(async move { watcher.watch("/foo", WatchOptions::default()).await });
+    // Advancing the clock lets the watch start before the key expires.
+    advance(Duration::from_secs(1)).await;
+    advance(Duration::from_secs(9)).await;
+
+    let response = watch.await.unwrap().unwrap();
+    assert_eq!(response.data.action, Action::Expire);
+    assert_eq!(response.data.prev_node.unwrap().value.unwrap(), "bar");
+
+    match store.get("/foo", GetOptions::default()).await {
+        Err(Error::Api(ref error)) => assert_eq!(error.error_code, 100),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn cleared_history() {
+    let store = MemoryStore::new();
+
+    for i in 0..1001 {
+        store.set("/foo", &i.to_string(), None).await.unwrap();
+    }
+
+    let options = WatchOptions {
+        index: Some(1),
+        ..Default::default()
+    };
+
+    match store.watch("/foo", options).await {
+        Err(WatchError::Other(Error::Api(ref error))) => assert_eq!(error.error_code, 401),
+        result => panic!("expected an API error, got {:?}", result),
+    }
+}
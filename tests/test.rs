@@ -1,11 +1,27 @@
+#[cfg(feature = "tls")]
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
 use std::ops::Deref;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
+#[cfg(feature = "rustls")]
+use etcd::rustls;
+#[cfg(feature = "tls")]
+use etcd::tls;
+#[cfg(any(feature = "tls", feature = "rustls"))]
+use etcd::tls::TlsConfig;
 use etcd::{kv, Client};
 use hyper::client::connect::Connect;
-use hyper::client::{Client as Hyper, HttpConnector};
+#[cfg(feature = "tls")]
+use hyper::client::Client as Hyper;
+use hyper::client::HttpConnector;
+#[cfg(feature = "tls")]
 use hyper_tls::HttpsConnector;
+#[cfg(feature = "tls")]
 use native_tls::{Certificate, Identity, TlsConnector};
 
 /// Wrapper around Client that automatically cleans up etcd after each test.
@@ -40,6 +56,7 @@ impl TestClient<HttpConnector> {
     }
 
     /// Creates a new HTTPS client for a test.
+    #[cfg(feature = "tls")]
     #[allow(dead_code)]
     pub fn https(use_client_cert: bool) -> TestClient<HttpsConnector<HttpConnector>> {
         let mut ca_cert_file = File::open("/source/tests/ssl/ca.der").unwrap();
@@ -70,6 +87,156 @@ impl TestClient<HttpConnector> {
             run_destructor: true,
         }
     }
+
+    /// Creates a new HTTPS client for a test from the PEM files of `tls_config`.
+    #[cfg(feature = "tls")]
+    #[allow(dead_code)]
+    pub fn https_with_config(use_client_cert: bool) -> TestClient<tls::HttpsConnector> {
+        TestClient {
+            c: Client::https_with_config(
+                &["https://etcdsecure:2379"],
+                None,
+                &tls_config(use_client_cert),
+            )
+            .unwrap(),
+            run_destructor: true,
+        }
+    }
+
+    /// Creates a new HTTPS client using rustls for a test.
+    #[cfg(feature = "rustls")]
+    #[allow(dead_code)]
+    pub fn https_rustls(use_client_cert: bool) -> TestClient<rustls::HttpsConnector> {
+        TestClient {
+            c: Client::https_rustls_with_config(
+                &["https://etcdsecure:2379"],
+                None,
+                &tls_config(use_client_cert),
+            )
+            .unwrap(),
+            run_destructor: true,
+        }
+    }
+}
+
+/// Returns the TLS configuration for the test cluster's HTTPS member.
+#[cfg(any(feature = "tls", feature = "rustls"))]
+#[allow(dead_code)]
+pub fn tls_config(use_client_cert: bool) -> TlsConfig {
+    let mut config = TlsConfig::new();
+    config.ca_file("/source/tests/ssl/ca.pem");
+
+    if use_client_cert {
+        config
+            .cert_file("/source/tests/ssl/client.pem")
+            .key_file("/source/tests/ssl/client-key.pem");
+    }
+
+    config
+}
+
+/// Starts a fake cluster member that sends each raw request it receives to the returned receiver,
+/// and answers it after `delay` with the status and JSON body `respond` returns for it.
+#[allow(dead_code)]
+pub fn fake_member<F>(delay: Duration, respond: F) -> (String, Receiver<String>)
+where
+    F: Fn(&str) -> (&'static str, &'static str) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = channel();
+    let respond = Arc::new(respond);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let sender = sender.clone();
+            let respond = respond.clone();
+
+            thread::spawn(move || {
+                let mut buffer = [0; 4096];
+                let length = stream.read(&mut buffer).unwrap();
+                let request = String::from_utf8_lossy(&buffer[..length]).to_string();
+                let (status, body) = respond(&request);
+                sender.send(request).unwrap();
+
+                thread::sleep(delay);
+
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+
+                stream.write_all(response.as_bytes()).unwrap();
+            });
+        }
+    });
+
+    (url, receiver)
+}
+
+/// Starts a fake cluster member that answers every request with the headers and the start of a
+/// body, and then stops sending without closing the connection.
+#[allow(dead_code)]
+pub fn stalled_member() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+
+            thread::spawn(move || {
+                let mut buffer = [0; 4096];
+                let _request_length = stream.read(&mut buffer).unwrap();
+                stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                          Content-Length: 100\r\n\r\n{\"action\":",
+                    )
+                    .unwrap();
+
+                // Keep the connection open until the client gives up on it.
+                io::copy(&mut stream, &mut io::sink()).ok();
+            });
+        }
+    });
+
+    url
+}
+
+/// Starts a fake cluster member that answers every request with a healthy health check right
+/// away, and sends each raw request it receives to the returned receiver.
+#[allow(dead_code)]
+pub fn recording_member() -> (String, Receiver<String>) {
+    fake_member(Duration::from_secs(0), |_| {
+        ("200 OK", r#"{"health":"true"}"#)
+    })
+}
+
+/// Returns the value of a header in a raw HTTP request.
+#[allow(dead_code)]
+pub fn header(request: &str, name: &str) -> Option<String> {
+    request.lines().find_map(|line| {
+        let mut parts = line.splitn(2, ':');
+        let line_name = parts.next()?;
+        let value = parts.next()?;
+
+        if line_name.eq_ignore_ascii_case(name) {
+            Some(value.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the path and query of a raw HTTP request.
+#[allow(dead_code)]
+pub fn request_path(request: &str) -> &str {
+    request.split_whitespace().nth(1).unwrap_or_default()
 }
 
 impl<C> Drop for TestClient<C>
@@ -0,0 +1,85 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use etcd::kv::{self, GetOptions};
+use etcd::{auth, health, members, stats, Client};
+use futures::future::try_join;
+use futures::stream::StreamExt;
+use hyper::client::connect::Connect;
+use tokio::io::copy;
+use tokio::net::{TcpStream, UnixListener};
+
+#[tokio::test]
+async fn api_calls() {
+    let client = client("api_calls");
+
+    kv::delete(&client, "/test/unix", true).await.ok();
+    kv::set(&client, "/test/unix/foo", "bar", None)
+        .await
+        .unwrap();
+
+    let res = kv::get(&client, "/test/unix/foo", GetOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(res.data.node.value.unwrap(), "bar");
+
+    kv::delete(&client, "/test/unix", true).await.unwrap();
+
+    let members = members::list(&client).await.unwrap().data;
+    assert!(!members.is_empty());
+
+    auth::status(&client).await.unwrap();
+
+    let mut store_stats = stats::store_stats(&client);
+    while let Some(response) = store_stats.next().await {
+        response.unwrap();
+    }
+
+    let mut health = client.health();
+    while let Some(response) = health.next().await {
+        assert_eq!(response.unwrap().data.health, "true");
+    }
+}
+
+#[tokio::test]
+async fn endpoints() {
+    let client = Client::unix(&["unix:///tmp/etcd.sock"], None).unwrap();
+
+    assert_eq!(client.endpoints(), vec!["unix:///tmp/etcd.sock"]);
+    assert_eq!(health::states(&client)[0].0, "unix:///tmp/etcd.sock");
+
+    client.add_endpoint("unix:///tmp/other.sock").unwrap();
+    client.remove_endpoint(&client.endpoints()[0]).unwrap();
+
+    assert_eq!(client.endpoints(), vec!["unix:///tmp/other.sock"]);
+}
+
+/// Returns a client connected through a Unix domain socket that is forwarded to etcd.
+fn client(name: &str) -> Client<impl Clone + Connect + Send + Sync + 'static> {
+    let path = env::temp_dir().join(format!("etcd-{}-{}.sock", process::id(), name));
+    fs::remove_file(&path).ok();
+
+    let mut listener = UnixListener::bind(&path).unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut inbound, _) = listener.accept().await.unwrap();
+
+            tokio::spawn(async move {
+                let mut outbound = TcpStream::connect("etcd:2379").await.unwrap();
+                let (mut inbound_reader, mut inbound_writer) = inbound.split();
+                let (mut outbound_reader, mut outbound_writer) = outbound.split();
+
+                try_join(
+                    copy(&mut inbound_reader, &mut outbound_writer),
+                    copy(&mut outbound_reader, &mut inbound_writer),
+                )
+                .await
+                .ok();
+            });
+        }
+    });
+
+    Client::unix(&[&format!("unix://{}", path.display())], None).unwrap()
+}